@@ -274,7 +274,16 @@ edit-layer-dialog-is-y-offset-label=Y Versatz:
 edit-layer-dialog-has-alpha-checkbox=Hat Alphakanal
 edit-layer-dialog-is-alpha-locked-checkbox=Alphakanal gesperrt
 
+plugin-parameter-dialog-title=Erweiterung { $title }
+plugin-parameter-dialog-run-button=Ausführen
+plugin-progress-dialog-title={ $title } wird ausgeführt
+plugin-progress-dialog-elapsed={ $seconds }s vergangen
+plugin-author=Autor: { $author }
+plugin-event-vetoed=Erweiterung { $title } hat abgelehnt: { $reason }
+
 error-load-file=Fehler während des Dateiladens: { $error }
+error-load-plugin=Fehler beim Laden der Erweiterung { $error }
+error-load-tool=Fehler beim Laden des Werkzeugs { $error }
 
 select-font-dialog-title=Font auswählen ({ $fontcount} verfügbar)
 add-font-dialog-title=Font hinzufügen ({ $fontcount} verfügbar)
@@ -322,6 +331,9 @@ pipette_tool_keys=
 
 char_table_tool_title=Zeichentabelle
 minimap_tool_title=Übersicht
+lua_console_tool_title=Lua Konsole
+lua_console_run_button=Ausführen
+lua_console_clear_button=Leeren
 
 no_document_selected=Kein Dokument ausgewählt
 
@@ -351,6 +363,9 @@ undo-render_character=Zeichen rendern
 undo-delete_character=Zeichen löschen
 undo-select=Auswahl
 undo-plugin=Erweiterung { $title }
+undo-lua-tool=Werkzeug { $title }
+undo-lua-console=Lua Konsole
+undo-update-layer-properties=Ebeneneigenschaften ändern
 
 font_selector-ansi_font=ANSI
 font_selector-library_font=LIBRARY
@@ -397,9 +412,41 @@ font-editor-flip_y=Y Spiegeln
 
 animation_editor_path_label=Datei:
 animation_editor_export_button=Export
+animation_editor_ansimation_frame_start=Bildanfang
+animation_editor_ansimation_frame_start_none=Nichts
+animation_editor_ansimation_frame_start_home=Cursor nach oben links
+animation_editor_ansimation_frame_start_clear=Bildschirm löschen
+animation_editor_diff_frames=Nur geänderte Zellen senden
+animation_editor_ansimation_baud_rate=Timing für Baudrate
+animation_editor_ansimation_baud_rate_off=Aus
+animation_editor_ansimation_use_macros=Bilder als DCS Makros senden (flackerfrei in IcyTerm)
+animation_editor_gif_dithering=Dithering
+animation_editor_gif_quality=Qualität
+animation_editor_gif_fast=Schnell
+animation_editor_gif_loop_count=Wiederholungen
+animation_editor_gif_loop_forever=(endlos)
+animation_editor_gif_scale=Skalierung
+animation_editor_gif_monitor_settings=CRT Filter der Monitoreinstellungen anwenden
 animation_editor_ansi_label=Ansimation
 animation_encoding_frame=Berechne Bild { $cur } von { $total }
+animation_rendering_frame=Rendere Bild { $cur } von { $total }
+animation_encoding_cancel=Abbrechen
 animation_of_frame_count=von { $total }
+animation_timeline_selection=Exportiere Bilder { $from } bis { $to }
+animation_timeline_clear_selection=Alle exportieren
+animation_timeline_hint=Klick zeigt ein Bild, Umschalt+Klick wählt die zu exportierenden Bilder
+animation_onion_skin=Zwiebelschicht
+animation_onion_skin_previous=Vorherige
+animation_onion_skin_next=Nächste
+animation_onion_skin_opacity=Deckkraft
+animation_debug_mode=Debuggen
+animation_debug_break_frame=Anhalten nach Bild
+animation_debug_run=Ausführen
+animation_debug_step=Schritt
+animation_debug_continue=Fortsetzen
+animation_debug_stopped=Angehalten nach Bild { $frame }
+animation_debug_globals=Globale Variablen
+animation_debug_layers=Ebenen
 animation_icy_play_note=Für Animationen in der Konsole/BBS (oder zum Ansi honvertieren) braucht man:
 
 new-file-template-cp437-title=CP437 ANSI
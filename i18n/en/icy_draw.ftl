@@ -267,7 +267,16 @@ edit-layer-dialog-is-y-offset-label=Y offset:
 edit-layer-dialog-has-alpha-checkbox=Has alpha
 edit-layer-dialog-is-alpha-locked-checkbox=Alpha locked
 
+plugin-parameter-dialog-title=Plugin { $title }
+plugin-parameter-dialog-run-button=Run
+plugin-progress-dialog-title=Running { $title }
+plugin-progress-dialog-elapsed={ $seconds }s elapsed
+plugin-author=Author: { $author }
+plugin-event-vetoed=Plugin { $title } vetoed: { $reason }
+
 error-load-file=Error loading file: { $error }
+error-load-plugin=Error loading plugin { $error }
+error-load-tool=Error loading tool { $error }
 
 select-font-dialog-title=Select Font ({ $fontcount} available)
 add-font-dialog-title=Add Font ({ $fontcount} available)
@@ -315,6 +324,9 @@ pipette_tool_keys=
 
 char_table_tool_title=Char table
 minimap_tool_title=Preview
+lua_console_tool_title=Lua console
+lua_console_run_button=Run
+lua_console_clear_button=Clear
 
 no_document_selected=No document selected
 
@@ -344,6 +356,9 @@ undo-render_character=Render character
 undo-delete_character=Delete character
 undo-select=Select
 undo-plugin=Plugin { $title }
+undo-lua-tool=Tool { $title }
+undo-lua-console=Lua console
+undo-update-layer-properties=Update layer properties
 
 font_selector-ansi_font=ANSI
 font_selector-library_font=LIBRARY
@@ -390,9 +405,41 @@ font-editor-flip_y=Flip Y
 
 animation_editor_path_label=Path:
 animation_editor_export_button=Export
+animation_editor_ansimation_frame_start=Frame start
+animation_editor_ansimation_frame_start_none=Nothing
+animation_editor_ansimation_frame_start_home=Home cursor
+animation_editor_ansimation_frame_start_clear=Clear screen
+animation_editor_diff_frames=Only send changed cells
+animation_editor_ansimation_baud_rate=Pace for baud rate
+animation_editor_ansimation_baud_rate_off=Off
+animation_editor_ansimation_use_macros=Send frames as DCS macros (flicker free in IcyTerm)
+animation_editor_gif_dithering=Dithering
+animation_editor_gif_quality=Quality
+animation_editor_gif_fast=Fast
+animation_editor_gif_loop_count=Loops
+animation_editor_gif_loop_forever=(forever)
+animation_editor_gif_scale=Scale
+animation_editor_gif_monitor_settings=Apply the CRT filters of the monitor settings
 animation_editor_ansi_label=Ansimation
 animation_encoding_frame=Encoding frame { $cur } of { $total }
+animation_rendering_frame=Rendering frame { $cur } of { $total }
+animation_encoding_cancel=Cancel
 animation_of_frame_count=of { $total }
+animation_timeline_selection=Exporting frames { $from } to { $to }
+animation_timeline_clear_selection=Export all
+animation_timeline_hint=Click a frame to show it, shift+click to select the frames to export
+animation_onion_skin=Onion skin
+animation_onion_skin_previous=Previous
+animation_onion_skin_next=Next
+animation_onion_skin_opacity=Opacity
+animation_debug_mode=Debug
+animation_debug_break_frame=Stop after frame
+animation_debug_run=Run
+animation_debug_step=Step
+animation_debug_continue=Continue
+animation_debug_stopped=Stopped after frame { $frame }
+animation_debug_globals=Globals
+animation_debug_layers=Layers
 animation_icy_play_note=Note: For playing the animation in the console/bbs or ansi conversion use:
 
 new-file-template-cp437-title=CP437 ANSI
@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use icy_engine_egui::animations::Animator;
+
+use super::create_parent_directory;
+use crate::{add_animation_library, render_frames, FrameStream, TerminalResult, ENCODERS};
+
+#[derive(Args, Debug)]
+pub struct AnimationArgs {
+    /// The .icyanim script
+    input: PathBuf,
+
+    /// Output file, defaults to the script name with the extension of the output format
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Output format (gif, png for an APNG, webp, json for a png sequence, cast, ans), defaults to the extension of the output file
+    #[arg(short, long)]
+    format: Option<String>,
+
+    /// Abort if the script didn't finish after this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    timeout: u64,
+}
+
+impl AnimationArgs {
+    pub fn run(&self) -> TerminalResult<()> {
+        let ext = match (&self.format, &self.output) {
+            (Some(format), _) => format.to_lowercase(),
+            (None, Some(output)) => output.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default(),
+            (None, None) => "gif".to_string(),
+        };
+        let Some(encoder) = ENCODERS.iter().position(|enc| enc.extension() == ext) else {
+            let formats = ENCODERS.iter().map(|enc| enc.extension()).collect::<Vec<String>>().join(", ");
+            return Err(anyhow::anyhow!("unsupported animation format '{ext}', supported: {formats}"));
+        };
+        let path = match &self.output {
+            Some(output) => output.clone(),
+            None => self.input.with_extension(&ext),
+        };
+
+        let txt = fs::read_to_string(&self.input)?;
+        let parent_path = self.input.parent().map(|p| p.to_path_buf());
+        let animator = Animator::run(&parent_path, add_animation_library(&txt));
+        let timeout = Duration::from_secs(self.timeout);
+        let start = Instant::now();
+        loop {
+            {
+                let animator = animator.lock().unwrap();
+                if !animator.error.is_empty() {
+                    return Err(anyhow::anyhow!("Error in animation script: {}", animator.error));
+                }
+                if animator.success() {
+                    break;
+                }
+            }
+            if start.elapsed() > timeout {
+                return Err(anyhow::anyhow!("Animation script didn't finish within {} seconds", self.timeout));
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        for line in &animator.lock().unwrap().log {
+            log::info!("Frame {}: {}", line.frame, line.text);
+        }
+
+        create_parent_directory(&path)?;
+        let frame_count = animator.lock().unwrap().frames.len();
+        if !ENCODERS[encoder].direct_encoding(&path, animator.clone(), 0..frame_count)? {
+            let (frames, width, height) = render_frames(&animator)?;
+            let frame_count = frames.len();
+            let (tx, rx) = std::sync::mpsc::channel();
+            let out_path = path.clone();
+            let handle = thread::Builder::new()
+                .name("Encoding".into())
+                .spawn(move || ENCODERS[encoder].encode(&out_path, FrameStream::from_frames(frames), width, height, tx))?;
+            for frame in rx {
+                eprint!("\rEncoding frame {} of {}", frame + 1, frame_count);
+            }
+            eprintln!();
+            match handle.join() {
+                Ok(result) => result?,
+                Err(_) => return Err(anyhow::anyhow!("Encoding thread panicked")),
+            }
+        }
+        println!("{} -> {}", self.input.display(), path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, ValueEnum};
+use icy_engine::{Buffer, SaveOptions, ScreenPreperation};
+
+use super::{create_parent_directory, get_output_file_name, process_files};
+use crate::TerminalResult;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ScreenPrep {
+    None,
+    Clear,
+    Home,
+}
+
+/// Save flags shared by the subcommands writing ansi files.
+#[derive(Args, Debug)]
+pub struct SaveArgs {
+    /// Compress the output
+    #[arg(long)]
+    compress: bool,
+
+    /// Use repeat sequences when compressing
+    #[arg(long, requires = "compress")]
+    repeat_sequences: bool,
+
+    /// Write UTF-8 output for modern terminals
+    #[arg(long)]
+    utf8: bool,
+
+    /// Append a SAUCE record
+    #[arg(long)]
+    sauce: bool,
+
+    /// Keep the full line length
+    #[arg(long)]
+    preserve_line_length: bool,
+
+    /// Limit the length of the output lines
+    #[arg(long, value_parser = clap::value_parser!(u16).range(32..=255))]
+    max_line_length: Option<u16>,
+
+    /// Screen preparation sequence written at the start of the file
+    #[arg(long, value_enum, default_value_t = ScreenPrep::None)]
+    screen_preparation: ScreenPrep,
+}
+
+impl SaveArgs {
+    pub fn get_save_options(&self) -> SaveOptions {
+        let mut options = SaveOptions::new();
+        options.compress = self.compress;
+        options.use_repeat_sequences = self.repeat_sequences;
+        options.modern_terminal_output = self.utf8;
+        options.save_sauce = self.sauce;
+        options.preserve_line_length = self.preserve_line_length;
+        options.output_line_length = self.max_line_length.map(|len| len as usize);
+        options.screen_preparation = match self.screen_preparation {
+            ScreenPrep::None => ScreenPreperation::None,
+            ScreenPrep::Clear => ScreenPreperation::ClearScreen,
+            ScreenPrep::Home => ScreenPreperation::Home,
+        };
+        options
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    /// Files to convert
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Output format as file extension (ans, xb, adf, idf, tnd, pcb, avt, bin, asc, msg, an1, icy)
+    #[arg(short, long)]
+    format: Option<String>,
+
+    /// Output file (only valid for a single input)
+    #[arg(short, long, conflicts_with = "output_dir")]
+    output: Option<PathBuf>,
+
+    /// Directory for the converted files, defaults to the directory of each input
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    save: SaveArgs,
+}
+
+impl ConvertArgs {
+    pub fn run(&self) -> TerminalResult<()> {
+        let options = self.save.get_save_options();
+        process_files(&self.inputs, &self.output, "convert", |input| self.convert_file(input, &options))
+    }
+
+    fn get_output_file(&self, input: &Path) -> TerminalResult<(PathBuf, String)> {
+        if let Some(output) = &self.output {
+            let ext = match &self.format {
+                Some(format) => format.to_lowercase(),
+                None => match output.extension() {
+                    Some(ext) => ext.to_string_lossy().to_lowercase(),
+                    None => return Err(anyhow::anyhow!("can't determine output format, use --format")),
+                },
+            };
+            return Ok((output.clone(), ext));
+        }
+
+        let Some(format) = &self.format else {
+            return Err(anyhow::anyhow!("--format is required when no --output file is given"));
+        };
+        let ext = format.to_lowercase();
+        Ok((get_output_file_name(input, &self.output_dir, &ext)?, ext))
+    }
+
+    fn convert_file(&self, input: &Path, options: &SaveOptions) -> TerminalResult<PathBuf> {
+        let (out_file, ext) = self.get_output_file(input)?;
+        let data = fs::read(input)?;
+        let buf = Buffer::from_bytes(input, true, &data)?;
+        let content = buf.to_bytes(&ext, options)?;
+        create_parent_directory(&out_file)?;
+        fs::write(&out_file, content)?;
+        Ok(out_file)
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use icy_engine::Rectangle;
+
+use crate::TerminalResult;
+
+mod animation;
+pub use animation::*;
+
+mod convert;
+pub use convert::*;
+
+mod plugin;
+pub use plugin::*;
+
+mod render;
+pub use render::*;
+
+mod test_plugin;
+pub use test_plugin::*;
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Convert files to another format without opening a window
+    Convert(ConvertArgs),
+    /// Render files to png or other image formats
+    Render(RenderArgs),
+    /// Run a plugin on files and save the result
+    Plugin(PluginArgs),
+    /// Run a plugin on a file and compare the result with an expected file
+    TestPlugin(TestPluginArgs),
+    /// Run an .icyanim script and export the animation
+    Animation(AnimationArgs),
+}
+
+impl CliCommand {
+    pub fn run(self) -> i32 {
+        let result = match self {
+            CliCommand::Convert(args) => args.run(),
+            CliCommand::Render(args) => args.run(),
+            CliCommand::Plugin(args) => args.run(),
+            CliCommand::TestPlugin(args) => args.run(),
+            CliCommand::Animation(args) => args.run(),
+        };
+        match result {
+            Ok(_) => 0,
+            Err(err) => {
+                log::error!("{err}");
+                eprintln!("error: {err}");
+                1
+            }
+        }
+    }
+}
+
+/// Runs process on every input file and reports the output files, a failing file doesn't stop the others.
+fn process_files(inputs: &[PathBuf], output: &Option<PathBuf>, action: &str, mut process: impl FnMut(&Path) -> TerminalResult<PathBuf>) -> TerminalResult<()> {
+    if output.is_some() && inputs.len() > 1 {
+        return Err(anyhow::anyhow!("--output can only be used with a single input file"));
+    }
+    let mut failed = 0;
+    for input in inputs {
+        match process(input) {
+            Ok(out_file) => {
+                println!("{} -> {}", input.display(), out_file.display());
+            }
+            Err(err) => {
+                log::error!("Failed to {action} {}: {err}", input.display());
+                eprintln!("{}: {err}", input.display());
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{failed} of {} files failed to {action}", inputs.len()));
+    }
+    Ok(())
+}
+
+/// Parses a rectangle given as "x,y,width,height".
+pub fn parse_rectangle(arg: &str) -> Result<Rectangle, String> {
+    let values = arg.split(',').map(|v| v.trim().parse::<i32>()).collect::<Result<Vec<i32>, _>>();
+    match values {
+        Ok(values) if values.len() == 4 && values[2] > 0 && values[3] > 0 => Ok(Rectangle::from(values[0], values[1], values[2], values[3])),
+        _ => Err(format!("invalid rectangle '{arg}', expected x,y,width,height")),
+    }
+}
+
+fn get_output_file_name(input: &Path, output_dir: &Option<PathBuf>, ext: &str) -> TerminalResult<PathBuf> {
+    let Some(stem) = input.file_stem() else {
+        return Err(anyhow::anyhow!("invalid file name"));
+    };
+    let dir = match output_dir {
+        Some(dir) => dir.clone(),
+        None => input.parent().map(|p| p.to_path_buf()).unwrap_or_default(),
+    };
+    let mut file_name = stem.to_os_string();
+    file_name.push(".");
+    file_name.push(ext);
+    Ok(dir.join(file_name))
+}
+
+fn create_parent_directory(file: &Path) -> TerminalResult<()> {
+    if let Some(parent) = file.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rectangle() {
+        assert_eq!(Ok(Rectangle::from(1, 2, 30, 40)), parse_rectangle("1,2,30,40"));
+        assert_eq!(Ok(Rectangle::from(-5, 0, 1, 1)), parse_rectangle(" -5 , 0, 1 ,1 "));
+        assert!(parse_rectangle("1,2,30").is_err());
+        assert!(parse_rectangle("1,2,30,40,50").is_err());
+        assert!(parse_rectangle("1,2,0,40").is_err());
+        assert!(parse_rectangle("1,2,30,-1").is_err());
+        assert!(parse_rectangle("a,b,c,d").is_err());
+        assert!(parse_rectangle("").is_err());
+    }
+
+    #[test]
+    fn test_output_file_name() {
+        assert_eq!(
+            PathBuf::from("art/logo.png"),
+            get_output_file_name(Path::new("art/logo.ans"), &None, "png").unwrap()
+        );
+        assert_eq!(PathBuf::from("logo.xb"), get_output_file_name(Path::new("logo.ans"), &None, "xb").unwrap());
+        assert_eq!(
+            PathBuf::from("logo.v2.png"),
+            get_output_file_name(Path::new("logo.v2.ans"), &None, "png").unwrap()
+        );
+        assert_eq!(PathBuf::from("logo.png"), get_output_file_name(Path::new("logo"), &None, "png").unwrap());
+        assert_eq!(
+            PathBuf::from("out/logo.png"),
+            get_output_file_name(Path::new("art/logo.ans"), &Some(PathBuf::from("out")), "png").unwrap()
+        );
+        assert!(get_output_file_name(Path::new(""), &None, "png").is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use clap::Args;
+use eframe::epaint::mutex::Mutex;
+use icy_engine::{editor::EditState, Buffer, Rectangle, SaveOptions};
+
+use super::{create_parent_directory, get_output_file_name, parse_rectangle, process_files, SaveArgs};
+use crate::{
+    plugins::{EditTarget, ExecutionControl, Plugin, PluginValue, PLUGIN_TIME_LIMIT},
+    TerminalResult,
+};
+
+#[derive(Args, Debug)]
+pub struct PluginArgs {
+    /// Plugin title, file name in the plugin directory or path to a lua script
+    plugin: String,
+
+    /// Files the plugin runs on
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Selection the plugin works on given as x,y,width,height - whole layer if omitted
+    #[arg(long, value_parser = parse_rectangle)]
+    rect: Option<Rectangle>,
+
+    /// Sets a plugin parameter, can be given multiple times - unset parameters use their last values
+    #[arg(long = "param", value_name = "NAME=VALUE")]
+    params: Vec<String>,
+
+    /// Abort the plugin after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    time_limit: Option<u64>,
+
+    /// Layer the plugin works on
+    #[arg(long)]
+    layer: Option<usize>,
+
+    /// Output format as file extension, defaults to the extension of the output file
+    #[arg(short, long)]
+    format: Option<String>,
+
+    /// Output file (only valid for a single input)
+    #[arg(short, long, conflicts_with = "output_dir")]
+    output: Option<PathBuf>,
+
+    /// Directory for the changed files
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// Overwrite the input files with the result
+    #[arg(long, conflicts_with_all = ["output", "output_dir"])]
+    in_place: bool,
+
+    #[command(flatten)]
+    save: SaveArgs,
+}
+
+impl PluginArgs {
+    pub fn run(&self) -> TerminalResult<()> {
+        if !self.in_place && self.output.is_none() && self.output_dir.is_none() && self.format.is_none() {
+            return Err(anyhow::anyhow!("no output given, use --output, --output-dir, --format or --in-place"));
+        }
+        Plugin::read_plugin_directory();
+        let plugin = Plugin::find(&self.plugin)?;
+        let parameter_values = parse_parameter_values(&plugin, &self.params)?;
+        let options = self.save.get_save_options();
+
+        process_files(&self.inputs, &self.output, "process", |input| {
+            self.run_plugin(&plugin, &parameter_values, input, &options)
+        })
+    }
+
+    fn get_output_file(&self, input: &Path) -> TerminalResult<PathBuf> {
+        if let Some(output) = &self.output {
+            return Ok(output.clone());
+        }
+        if self.in_place && self.output_dir.is_none() && self.format.is_none() {
+            return Ok(input.to_path_buf());
+        }
+        let ext = match &self.format {
+            Some(format) => format.to_lowercase(),
+            None => input.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default(),
+        };
+        let out_file = get_output_file_name(input, &self.output_dir, &ext)?;
+        if !self.in_place && out_file == input {
+            return Err(anyhow::anyhow!("the result would overwrite the input file, use --in-place to allow that"));
+        }
+        Ok(out_file)
+    }
+
+    fn run_plugin(&self, plugin: &Plugin, parameter_values: &[(String, PluginValue)], input: &Path, options: &SaveOptions) -> TerminalResult<PathBuf> {
+        let out_file = self.get_output_file(input)?;
+        let state = run_headless(plugin, parameter_values, input, self.layer, self.rect, self.time_limit)?;
+
+        let ext = match &self.format {
+            Some(format) => format.to_lowercase(),
+            None => out_file.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default(),
+        };
+        let content = state.lock().get_buffer().to_bytes(&ext, options)?;
+        create_parent_directory(&out_file)?;
+        fs::write(&out_file, content)?;
+        Ok(out_file)
+    }
+}
+
+/// Applies "NAME=VALUE" arguments to the last used parameter values of the plugin.
+pub(super) fn parse_parameter_values(plugin: &Plugin, params: &[String]) -> TerminalResult<Vec<(String, PluginValue)>> {
+    let mut parameter_values = plugin.get_parameter_values();
+    for param in params {
+        let Some((name, value)) = param.split_once('=') else {
+            return Err(anyhow::anyhow!("invalid parameter '{param}', expected NAME=VALUE"));
+        };
+        let Some(i) = plugin.parameters.iter().position(|p| p.name == name.trim()) else {
+            return Err(anyhow::anyhow!("plugin has no parameter '{}'", name.trim()));
+        };
+        parameter_values[i].1 = plugin.parameters[i].parse_value(value)?;
+    }
+    Ok(parameter_values)
+}
+
+/// Runs a plugin on a file without an editor window and returns the changed edit state.
+pub(super) fn run_headless(
+    plugin: &Plugin,
+    parameter_values: &[(String, PluginValue)],
+    input: &Path,
+    layer: Option<usize>,
+    rect: Option<Rectangle>,
+    time_limit: Option<u64>,
+) -> TerminalResult<Arc<Mutex<EditState>>> {
+    let data = fs::read(input)?;
+    let mut buf = Buffer::from_bytes(input, true, &data)?;
+    buf.is_terminal_buffer = false;
+    let layer_count = buf.layers.len();
+
+    let mut state = EditState::from_buffer(buf);
+    if let Some(layer) = layer {
+        if layer >= layer_count {
+            return Err(anyhow::anyhow!("Layer {} out of range (0..<{})", layer, layer_count));
+        }
+        state.set_current_layer(layer);
+    }
+    if let Some(rect) = rect {
+        state.set_selection(rect)?;
+    }
+
+    let state = Arc::new(Mutex::new(state));
+    let time_limit = time_limit.map(Duration::from_secs).unwrap_or(PLUGIN_TIME_LIMIT);
+    plugin.run(EditTarget::Headless(state.clone()), parameter_values, &ExecutionControl::new(Some(time_limit)))?;
+    Ok(state)
+}
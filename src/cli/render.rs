@@ -0,0 +1,164 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use icy_engine::{Buffer, Rectangle};
+use image::DynamicImage;
+
+use super::{create_parent_directory, get_output_file_name, parse_rectangle, process_files};
+use crate::{
+    util::render::{render_to_image, RenderOptions},
+    TerminalResult,
+};
+
+#[derive(Args, Debug)]
+pub struct RenderArgs {
+    /// Files to render
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Image format as file extension (png, gif, jpg, bmp, tga, tiff, webp, qoi, …)
+    #[arg(short, long, default_value = "png")]
+    format: String,
+
+    /// Output file (only valid for a single input)
+    #[arg(short, long, conflicts_with = "output_dir")]
+    output: Option<PathBuf>,
+
+    /// Directory for the rendered images, defaults to the directory of each input
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// Only render these layers (comma separated layer numbers)
+    #[arg(long, value_delimiter = ',', conflicts_with = "hide_layers")]
+    layers: Option<Vec<usize>>,
+
+    /// Hide these layers (comma separated layer numbers)
+    #[arg(long, value_delimiter = ',')]
+    hide_layers: Vec<usize>,
+
+    /// Render a part of the buffer given as x,y,width,height
+    #[arg(long, value_parser = parse_rectangle)]
+    rect: Option<Rectangle>,
+
+    /// Render in 9px font mode (--letter-spacing=false to turn it off), defaults to the SAUCE setting of the file
+    #[arg(long = "letter-spacing", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    letter_spacing: Option<bool>,
+
+    /// Correct the aspect ratio for legacy displays (--aspect-ratio=false to turn it off), defaults to the SAUCE setting of the file
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    aspect_ratio: Option<bool>,
+
+    /// Integer scale factor
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=16))]
+    scale: u32,
+}
+
+impl RenderArgs {
+    pub fn run(&self) -> TerminalResult<()> {
+        process_files(&self.inputs, &self.output, "render", |input| self.render_file(input))
+    }
+
+    fn render_file(&self, input: &Path) -> TerminalResult<PathBuf> {
+        let out_file = match &self.output {
+            Some(output) => output.clone(),
+            None => get_output_file_name(input, &self.output_dir, &self.format.to_lowercase())?,
+        };
+
+        let data = fs::read(input)?;
+        let mut buf = Buffer::from_bytes(input, true, &data)?;
+        self.set_layer_visibility(&mut buf)?;
+
+        let img = render_to_image(&buf, &self.get_render_options(&buf))?;
+
+        create_parent_directory(&out_file)?;
+        let ext = out_file.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let result = match ext.as_str() {
+            // these formats don't support an alpha channel
+            "jpg" | "jpeg" | "pnm" | "ppm" | "pgm" | "pbm" => DynamicImage::ImageRgba8(img).to_rgb8().save(&out_file),
+            _ => img.save(&out_file),
+        };
+        if let Err(err) = result {
+            return Err(anyhow::anyhow!("Failed to save image: {err}"));
+        }
+        Ok(out_file)
+    }
+
+    /// The options of the buffer, overridden by the given arguments.
+    fn get_render_options(&self, buf: &Buffer) -> RenderOptions {
+        let mut options = RenderOptions::from_buffer(buf);
+        options.rect = self.rect;
+        options.scale = self.scale;
+        if let Some(letter_spacing) = self.letter_spacing {
+            options.use_letter_spacing = letter_spacing;
+        }
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            options.use_aspect_ratio = aspect_ratio;
+        }
+        options
+    }
+
+    fn set_layer_visibility(&self, buf: &mut Buffer) -> TerminalResult<()> {
+        let layer_count = buf.layers.len();
+        let check_layer = |layer: usize| {
+            if layer >= layer_count {
+                Err(anyhow::anyhow!("Layer {} out of range (0..<{})", layer, layer_count))
+            } else {
+                Ok(())
+            }
+        };
+
+        if let Some(layers) = &self.layers {
+            for layer in layers {
+                check_layer(*layer)?;
+            }
+            for (i, layer) in buf.layers.iter_mut().enumerate() {
+                layer.is_visible = layers.contains(&i);
+            }
+        }
+        for layer in &self.hide_layers {
+            check_layer(*layer)?;
+            buf.layers[*layer].is_visible = false;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        args: RenderArgs,
+    }
+
+    fn parse(args: &[&str]) -> RenderArgs {
+        TestCli::try_parse_from(["icy_draw"].iter().chain(args)).unwrap().args
+    }
+
+    #[test]
+    fn test_letter_spacing_argument() {
+        assert_eq!(None, parse(&["a.ans"]).letter_spacing);
+        assert_eq!(Some(true), parse(&["--letter-spacing", "a.ans"]).letter_spacing);
+        assert_eq!(Some(true), parse(&["a.ans", "--letter-spacing"]).letter_spacing);
+        assert_eq!(Some(false), parse(&["--letter-spacing=false", "a.ans"]).letter_spacing);
+        assert_eq!(vec![PathBuf::from("a.ans")], parse(&["--letter-spacing", "a.ans"]).inputs);
+        assert!(TestCli::try_parse_from(["icy_draw", "--letter-spacing=maybe", "a.ans"]).is_err());
+    }
+
+    #[test]
+    fn test_letter_spacing_options() {
+        let buf = Buffer::new((80, 25));
+        let default = RenderOptions::from_buffer(&buf);
+        assert_eq!(default.use_letter_spacing, parse(&["a.ans"]).get_render_options(&buf).use_letter_spacing);
+        assert!(parse(&["--letter-spacing", "a.ans"]).get_render_options(&buf).use_letter_spacing);
+        assert!(!parse(&["--letter-spacing=false", "a.ans"]).get_render_options(&buf).use_letter_spacing);
+        assert_eq!(2, parse(&["--scale", "2", "a.ans"]).get_render_options(&buf).scale);
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use icy_engine::{AttributedChar, Buffer, BufferType, Position, Rectangle, SaveOptions, TextPane};
+
+use super::{create_parent_directory, parse_parameter_values, parse_rectangle, run_headless};
+use crate::{
+    plugins::{convert_to_unicode, Plugin},
+    TerminalResult,
+};
+
+#[derive(Args, Debug)]
+pub struct TestPluginArgs {
+    /// Plugin title, file name in the plugin directory or path to a lua script
+    plugin: String,
+
+    /// File the plugin runs on
+    input: PathBuf,
+
+    /// File with the expected result, its extension gives the format the result is compared in
+    expected: PathBuf,
+
+    /// Selection the plugin works on given as x,y,width,height - whole layer if omitted
+    #[arg(long, value_parser = parse_rectangle)]
+    rect: Option<Rectangle>,
+
+    /// Sets a plugin parameter, can be given multiple times - unset parameters use their last values
+    #[arg(long = "param", value_name = "NAME=VALUE")]
+    params: Vec<String>,
+
+    /// Abort the plugin after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    time_limit: Option<u64>,
+
+    /// Layer the plugin works on
+    #[arg(long)]
+    layer: Option<usize>,
+
+    /// Write the result to the expected file instead of comparing
+    #[arg(long)]
+    update: bool,
+}
+
+impl TestPluginArgs {
+    pub fn run(&self) -> TerminalResult<()> {
+        Plugin::read_plugin_directory();
+        let plugin = Plugin::find(&self.plugin)?;
+        let parameter_values = parse_parameter_values(&plugin, &self.params)?;
+        let state = run_headless(&plugin, &parameter_values, &self.input, self.layer, self.rect, self.time_limit)?;
+
+        let Some(ext) = self.expected.extension().map(|ext| ext.to_string_lossy().to_lowercase()) else {
+            return Err(anyhow::anyhow!("can't determine the format of {}", self.expected.display()));
+        };
+        // default options keep the files independent from the save settings
+        let content = state.lock().get_buffer().to_bytes(&ext, &SaveOptions::new())?;
+        if self.update {
+            create_parent_directory(&self.expected)?;
+            fs::write(&self.expected, content)?;
+            println!("{} -> {}", self.input.display(), self.expected.display());
+            return Ok(());
+        }
+
+        // loading the saved result again flattens it the same way the expected file got flattened
+        let actual = Buffer::from_bytes(&self.expected, true, &content)?;
+        let expected = load_buffer(&self.expected)?;
+        match compare_buffers(&expected, &actual) {
+            None => {
+                println!("ok {}", self.input.display());
+                Ok(())
+            }
+            Some(difference) => {
+                eprintln!("{difference}");
+                eprint!("{}", text_diff(&expected, &actual));
+                Err(anyhow::anyhow!("result of {} differs from {}", self.input.display(), self.expected.display()))
+            }
+        }
+    }
+}
+
+fn load_buffer(file: &Path) -> TerminalResult<Buffer> {
+    let data = fs::read(file)?;
+    Buffer::from_bytes(file, true, &data)
+}
+
+/// Compares two buffers cell by cell, returns a description of the size difference and the first differing cell.
+pub(crate) fn compare_buffers(expected: &Buffer, actual: &Buffer) -> Option<String> {
+    let mut result = String::new();
+    if expected.get_width() != actual.get_width() || expected.get_height() != actual.get_height() {
+        let _ = writeln!(
+            result,
+            "size differs: expected {}x{}, got {}x{}",
+            expected.get_width(),
+            expected.get_height(),
+            actual.get_width(),
+            actual.get_height()
+        );
+    }
+
+    let width = expected.get_width().min(actual.get_width());
+    let height = expected.get_height().min(actual.get_height());
+    let mut first = None;
+    let mut count = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let pos = Position::new(x, y);
+            let (e, a) = (expected.get_char(pos), actual.get_char(pos));
+            if e != a {
+                count += 1;
+                if first.is_none() {
+                    first = Some((pos, e, a));
+                }
+            }
+        }
+    }
+    if let Some((pos, e, a)) = first {
+        let _ = writeln!(result, "{count} cells differ, first at x {} y {}:", pos.x, pos.y);
+        let _ = writeln!(result, "  expected {}", describe_char(expected.buffer_type, e));
+        let _ = writeln!(result, "  got      {}", describe_char(actual.buffer_type, a));
+    }
+
+    if result.is_empty() {
+        None
+    } else {
+        Some(result.trim_end().to_string())
+    }
+}
+
+fn describe_char(buffer_type: BufferType, ch: AttributedChar) -> String {
+    format!(
+        "'{}' (0x{:02X}) fg {} bg {} font page {} attr 0x{:04X}",
+        convert_to_unicode(buffer_type, ch),
+        ch.ch as u32,
+        ch.attribute.get_foreground(),
+        ch.attribute.get_background(),
+        ch.get_font_page(),
+        ch.attribute.attr
+    )
+}
+
+/// Shows the lines that differ in their chars, attribute only changes are covered by the cell comparison.
+fn text_diff(expected: &Buffer, actual: &Buffer) -> String {
+    let mut result = String::new();
+    for y in 0..expected.get_height().max(actual.get_height()) {
+        let e = get_line(expected, y);
+        let a = get_line(actual, y);
+        if e != a {
+            let _ = writeln!(result, "{:>4} - {}", y + 1, e.unwrap_or_default());
+            let _ = writeln!(result, "{:>4} + {}", y + 1, a.unwrap_or_default());
+        }
+    }
+    result
+}
+
+fn get_line(buf: &Buffer, y: i32) -> Option<String> {
+    if y >= buf.get_height() {
+        return None;
+    }
+    let line: String = (0..buf.get_width())
+        .map(|x| convert_to_unicode(buf.buffer_type, buf.get_char(Position::new(x, y))))
+        .collect();
+    Some(line.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use icy_engine::TextAttribute;
+
+    use super::*;
+
+    fn create_buffer(width: i32, height: i32) -> Buffer {
+        let mut buf = Buffer::new((width, height));
+        let mut attr = TextAttribute::default();
+        attr.set_foreground(14);
+        attr.set_background(1);
+        buf.layers[0].set_char((1, 0), AttributedChar::new('A', attr));
+        buf
+    }
+
+    #[test]
+    fn test_compare_equal_buffers() {
+        assert_eq!(None, compare_buffers(&create_buffer(4, 2), &create_buffer(4, 2)));
+        assert_eq!("", text_diff(&create_buffer(4, 2), &create_buffer(4, 2)));
+    }
+
+    #[test]
+    fn test_compare_size() {
+        assert_eq!(
+            Some("size differs: expected 4x2, got 5x2".to_string()),
+            compare_buffers(&create_buffer(4, 2), &create_buffer(5, 2))
+        );
+    }
+
+    #[test]
+    fn test_compare_attribute() {
+        let expected = create_buffer(4, 2);
+        let mut actual = create_buffer(4, 2);
+        let mut ch = actual.get_char(Position::new(1, 0));
+        ch.attribute.set_is_bold(true);
+        actual.layers[0].set_char((1, 0), ch);
+        actual.layers[0].set_char((3, 1), AttributedChar::new('B', TextAttribute::default()));
+
+        let difference = compare_buffers(&expected, &actual).unwrap();
+        let lines: Vec<&str> = difference.lines().collect();
+        assert_eq!(3, lines.len());
+        assert_eq!("2 cells differ, first at x 1 y 0:", lines[0]);
+        assert!(lines[1].starts_with("  expected 'A' (0x41) fg 14 bg 1 "));
+        assert!(lines[2].starts_with("  got      'A' (0x41) fg 14 bg 1 "));
+        assert_ne!(lines[1].split(" attr ").nth(1), lines[2].split(" attr ").nth(1));
+
+        // the attribute change isn't a text change
+        assert_eq!("   2 - \n   2 +    B\n", text_diff(&expected, &actual));
+    }
+}
@@ -6,6 +6,7 @@ use std::path::PathBuf;
 
 use eframe::egui;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+mod cli;
 mod model;
 mod paint;
 mod plugins;
@@ -48,8 +49,12 @@ static LANGUAGE_LOADER: Lazy<FluentLanguageLoader> = Lazy::new(|| {
 use clap::Parser;
 
 #[derive(Parser, Debug)]
+#[command(version, args_conflicts_with_subcommands = true)]
 pub struct Cli {
     path: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<cli::CliCommand>,
 }
 
 // When compiling natively:
@@ -60,7 +65,6 @@ fn main() {
 
     let args = Cli::parse();
 
-    use crate::plugins::Plugin;
     let options = eframe::NativeOptions {
         initial_window_size: Some(egui::vec2(1280., 841.)),
         multisampling: 0,
@@ -148,6 +152,16 @@ fn main() {
         }
     }
 
+    if let Ok(settings_file) = PluginParameterValues::get_plugin_parameters_file() {
+        if settings_file.exists() {
+            if let Ok(plugin_parameters) = PluginParameterValues::load(&settings_file) {
+                unsafe {
+                    PLUGIN_PARAMETERS = plugin_parameters;
+                }
+            }
+        }
+    }
+
     unsafe {
         if KEYBINDINGS.key_bindings.is_empty() {
             KEYBINDINGS.key_bindings = Commands::default_keybindings();
@@ -158,8 +172,11 @@ fn main() {
         }
     }
 
+    if let Some(command) = args.command {
+        std::process::exit(command.run());
+    }
+
     log::info!("Starting iCY DRAW {}", VERSION);
-    Plugin::read_plugin_directory();
     if let Err(err) = eframe::run_native(
         &DEFAULT_TITLE,
         options,
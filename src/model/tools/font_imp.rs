@@ -1,4 +1,13 @@
-use std::{fs, io::Read, path::Path, sync::Arc, thread};
+use std::{
+    fs,
+    io::Read,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
 
 use crate::{AnsiEditor, Message, Settings};
 
@@ -12,6 +21,12 @@ use i18n_embed_fl::fl;
 use icy_engine::{editor::OperationType, Size, TextPane, TheDrawFont};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use walkdir::{DirEntry, WalkDir};
+
+lazy_static::lazy_static! {
+    static ref TDF_FONTS: Arc<Mutex<Vec<TheDrawFont>>> = Arc::new(Mutex::new(Vec::new()));
+}
+static TDF_FONTS_LOADED: AtomicBool = AtomicBool::new(false);
+
 pub struct FontTool {
     pub selected_font: Arc<Mutex<i32>>,
     pub fonts: Arc<Mutex<Vec<TheDrawFont>>>,
@@ -39,9 +54,17 @@ impl FontTool {
     }
 
     pub fn load_fonts(&mut self) {
-        if let Ok(tdf_dir) = Settings::get_tdf_diretory() {
-            self.fonts = Arc::new(Mutex::new(load_fonts(tdf_dir.as_path())));
+        self.fonts = FontTool::get_fonts();
+    }
+
+    /// The fonts of the tdf directory, shared between the font tool and the Lua API. They're loaded on first use.
+    pub fn get_fonts() -> Arc<Mutex<Vec<TheDrawFont>>> {
+        if !TDF_FONTS_LOADED.swap(true, Ordering::SeqCst) {
+            if let Ok(tdf_dir) = Settings::get_tdf_diretory() {
+                *TDF_FONTS.lock() = load_fonts(tdf_dir.as_path());
+            }
         }
+        TDF_FONTS.clone()
     }
 }
 
@@ -19,3 +19,28 @@ lazy_static::lazy_static! {
     pub static ref SELECT_SVG: Image<'static> = Image::new(egui::include_image!("../../../data/icons/select.svg"));
     pub static ref FLIP_TOOL_SVG: Image<'static> = Image::new(egui::include_image!("../../../data/icons/flip_tool.svg"));
 }
+
+/// Looks up a tool icon by name, used by the lua tools.
+pub fn get_icon(name: &str) -> Option<&'static Image<'static>> {
+    let icon: &'static Image<'static> = match name.trim().to_ascii_lowercase().as_str() {
+        "add" => &ADD_SVG,
+        "pencil" => &PENCIL_SVG,
+        "brush" => &BRUSH_SVG,
+        "text" => &TEXT_SVG,
+        "cursor" => &CURSOR_SVG,
+        "dropper" => &DROPPER_SVG,
+        "ellipse_filled" => &ELLIPSE_FILLED_SVG,
+        "ellipse_outline" => &ELLIPSE_OUTLINE_SVG,
+        "eraser" => &ERASER_SVG,
+        "fill" => &FILL_SVG,
+        "line" => &LINE_SVG,
+        "font" => &FONT_SVG,
+        "move" => &MOVE_SVG,
+        "rectangle_filled" => &RECTANGLE_FILLED_SVG,
+        "rectangle_outline" => &RECTANGLE_OUTLINE_SVG,
+        "select" => &SELECT_SVG,
+        "flip" => &FLIP_TOOL_SVG,
+        _ => return None,
+    };
+    Some(icon)
+}
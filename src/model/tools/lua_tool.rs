@@ -0,0 +1,222 @@
+use std::{fs, path::Path, time::Duration};
+
+use eframe::egui::{self, Layout, Response};
+use i18n_embed_fl::fl;
+use icy_engine::editor::AtomicUndoGuard;
+use icy_engine_egui::TerminalCalc;
+use mlua::{IntoLuaMulti, Lua};
+
+use crate::{
+    plugins::{
+        format_lua_error, get_header, read_script_directory, set_parameter_globals, setup_lua, update_lua, EditTarget, ExecutionControl, PluginParameter,
+        PluginValue,
+    },
+    show_parameter, AnsiEditor, Event, Message, Settings,
+};
+
+use super::{icons, Position, Tool};
+
+/// Tool handlers run on the UI thread, endless loops get aborted after this time.
+const TOOL_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// A tool implemented by a lua script in the tools directory:
+///
+/// -- Title: Spray
+/// -- Icon: brush
+/// -- Tooltip: Sprays random dots
+/// -- Param: density: int(1, 100) = 30 "Density"
+///
+/// The script defines the handlers on_click(x, y, button), on_drag_begin(x, y), on_drag(x, y),
+/// on_drag_end(x, y) and on_hover(x, y). Coordinates are current layer coordinates.
+pub struct LuaTool {
+    title: String,
+    tooltip: String,
+    icon: &'static egui::Image<'static>,
+    chunk_name: String,
+    text: String,
+    parameters: Vec<PluginParameter>,
+    values: Vec<PluginValue>,
+
+    lua: Lua,
+    initialized: bool,
+    undo_op: Option<AtomicUndoGuard>,
+    cur_pos: Position,
+    error: Option<String>,
+}
+
+impl LuaTool {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let Some(title) = get_header(&text, "Title")? else {
+            return Err(anyhow::anyhow!("No tool file"));
+        };
+        let icon = match get_header(&text, "Icon")? {
+            Some(name) => icons::get_icon(&name).ok_or_else(|| anyhow::anyhow!("Unknown icon '{name}'"))?,
+            None => &icons::BRUSH_SVG,
+        };
+        let parameters = PluginParameter::parse_header(&text)?;
+        let values = parameters.iter().map(|p| p.default.clone()).collect();
+        let chunk_name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => title.clone(),
+        };
+
+        let lua = Lua::new();
+        // only check the syntax here, the script runs with the editor environment on first use
+        if let Err(err) = lua.load(&text).set_name(format!("={chunk_name}")).into_function() {
+            return Err(anyhow::anyhow!("{}", format_lua_error(&err, &chunk_name)));
+        }
+
+        Ok(Self {
+            tooltip: get_header(&text, "Tooltip")?.unwrap_or_default(),
+            title,
+            icon,
+            chunk_name,
+            text,
+            parameters,
+            values,
+            lua,
+            initialized: false,
+            undo_op: None,
+            cur_pos: Position::new(-1, -1),
+            error: None,
+        })
+    }
+
+    /// Loads all tools of the tools directory and returns them together with the errors of the tools that failed to load.
+    pub fn read_tools_directory() -> (Vec<LuaTool>, Vec<String>) {
+        let Ok(root) = Settings::get_tools_directory() else {
+            log::error!("Can't read tools directory.");
+            return (Vec::new(), Vec::new());
+        };
+        read_script_directory(&root, LuaTool::load)
+    }
+
+    /// Calls a handler of the script if it's defined, errors are kept to be shown in the tool panel.
+    fn call_handler(&mut self, editor: &AnsiEditor, handler: &str, args: impl for<'lua> IntoLuaMulti<'lua>) -> Option<Message> {
+        match self.try_call_handler(editor, handler, args) {
+            Ok(()) => {
+                self.error = None;
+                None
+            }
+            Err(err) => {
+                let msg = format!("{}: {err}", self.title);
+                self.error = Some(msg.clone());
+                Some(Message::ShowError(msg))
+            }
+        }
+    }
+
+    fn try_call_handler(&mut self, editor: &AnsiEditor, handler: &str, args: impl for<'lua> IntoLuaMulti<'lua>) -> anyhow::Result<()> {
+        let target = EditTarget::View(editor.buffer_view.clone());
+        let control = ExecutionControl::new(Some(TOOL_TIME_LIMIT));
+        let chunk_name = self.chunk_name.clone();
+        let lua_error = |err: mlua::Error| anyhow::anyhow!("{}", format_lua_error(&err, &chunk_name));
+
+        // the state lives as long as the tool, the script runs once and later calls only update the editor environment
+        let values = self.parameters.iter().map(|p| p.name.as_str()).zip(self.values.iter());
+        if self.initialized {
+            update_lua(&self.lua, target, &control)?;
+            set_parameter_globals(&self.lua, values)?;
+        } else {
+            setup_lua(&self.lua, target, &control)?;
+            set_parameter_globals(&self.lua, values)?;
+            self.lua.load(&self.text).set_name(format!("={chunk_name}")).exec().map_err(lua_error)?;
+            self.initialized = true;
+        }
+
+        if let Some(function) = self.lua.globals().get::<_, Option<mlua::Function>>(handler)? {
+            function.call::<_, ()>(args).map_err(lua_error)?;
+        }
+        Ok(())
+    }
+
+    fn undo_description(&self) -> String {
+        fl!(crate::LANGUAGE_LOADER, "undo-lua-tool", title = self.title.clone())
+    }
+}
+
+impl Tool for LuaTool {
+    fn get_icon(&self) -> &egui::Image<'static> {
+        self.icon
+    }
+
+    fn tool_name(&self) -> String {
+        self.title.clone()
+    }
+
+    fn tooltip(&self) -> String {
+        self.tooltip.clone()
+    }
+
+    fn use_caret(&self) -> bool {
+        false
+    }
+
+    fn show_ui(&mut self, _ctx: &egui::Context, ui: &mut egui::Ui, editor_opt: Option<&mut AnsiEditor>) -> Option<Message> {
+        let palette: Vec<(u8, u8, u8)> = match editor_opt {
+            Some(editor) => editor.buffer_view.lock().get_buffer().palette.color_iter().map(|c| c.get_rgb()).collect(),
+            None => Vec::new(),
+        };
+
+        egui::Grid::new("lua_tool_parameter_grid")
+            .num_columns(2)
+            .spacing([4.0, 8.0])
+            .min_row_height(24.)
+            .show(ui, |ui| {
+                for (i, (parameter, value)) in self.parameters.iter().zip(self.values.iter_mut()).enumerate() {
+                    ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.label(&parameter.label);
+                    });
+                    show_parameter(ui, i, parameter, value, &palette);
+                    ui.end_row();
+                }
+            });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        None
+    }
+
+    fn handle_click(&mut self, editor: &mut AnsiEditor, button: i32, pos: Position, _pos_abs: Position, _response: &Response) -> Option<Message> {
+        let _op: AtomicUndoGuard = editor.begin_atomic_undo(self.undo_description());
+        self.call_handler(editor, "on_click", (pos.x, pos.y, button))
+    }
+
+    fn handle_drag_begin(&mut self, editor: &mut AnsiEditor, _response: &egui::Response) -> Event {
+        self.undo_op = Some(editor.begin_atomic_undo(self.undo_description()));
+        let pos = editor.drag_pos.start;
+        self.call_handler(editor, "on_drag_begin", (pos.x, pos.y));
+        Event::None
+    }
+
+    fn handle_drag(&mut self, _ui: &egui::Ui, response: Response, editor: &mut AnsiEditor, _calc: &TerminalCalc) -> Response {
+        if self.error.is_none() {
+            let pos = editor.drag_pos.cur;
+            self.call_handler(editor, "on_drag", (pos.x, pos.y));
+        }
+        response
+    }
+
+    fn handle_hover(&mut self, _ui: &egui::Ui, response: Response, editor: &mut AnsiEditor, cur: Position, _cur_abs: Position) -> Response {
+        if self.cur_pos != cur {
+            self.cur_pos = cur;
+            // on_hover shouldn't change the buffer, but if it does the change can be undone like a click
+            let _op: AtomicUndoGuard = editor.begin_atomic_undo(self.undo_description());
+            self.call_handler(editor, "on_hover", (cur.x, cur.y));
+        }
+        response.on_hover_cursor(egui::CursorIcon::Crosshair)
+    }
+
+    fn handle_no_hover(&mut self, _editor: &mut AnsiEditor) {
+        self.cur_pos = Position::new(-1, -1);
+    }
+
+    fn handle_drag_end(&mut self, editor: &mut AnsiEditor) -> Option<Message> {
+        let pos = editor.drag_pos.cur;
+        let msg = self.call_handler(editor, "on_drag_end", (pos.x, pos.y));
+        self.undo_op = None;
+        msg
+    }
+}
@@ -9,6 +9,7 @@ pub mod fill_imp;
 pub mod flip_imp;
 pub mod font_imp;
 pub mod line_imp;
+pub mod lua_tool;
 pub mod move_layer_imp;
 pub mod paste_tool;
 pub mod pencil_imp;
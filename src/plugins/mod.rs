@@ -1,113 +1,812 @@
-use std::{fs, path::Path, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
+use eframe::{
+    egui::{self, Modifiers},
+    epaint::mutex::{Mutex, MutexGuard},
+};
 use i18n_embed_fl::fl;
-use icy_engine::{attribute, AttributedChar, Position, TextPane, UnicodeConverter};
-use mlua::{Lua, UserData};
+use icy_engine::{
+    attribute, editor::EditState, AttributedChar, BitFont, Color, IceMode, Mode, Palette, PaletteMode, Position, Rectangle, SauceData, SauceString, TextPane,
+    TheDrawFont, UnicodeConverter,
+};
+use icy_engine_egui::BufferView;
+use mlua::{HookTriggers, Lua, UserData};
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use regex::Regex;
 use walkdir::WalkDir;
 
-use crate::{model::font_imp::FontTool, Settings, PLUGINS};
+use crate::{model::font_imp::FontTool, Settings, PLUGINS, PLUGIN_PARAMETERS};
 
+mod parameters;
+pub use parameters::*;
+
+mod undo;
+pub use undo::*;
+
+/// Plugins get aborted after this time, the user can cancel earlier.
+pub const PLUGIN_TIME_LIMIT: Duration = Duration::from_secs(300);
+
+/// Event callbacks block the UI, so they get a much shorter time limit.
+const PLUGIN_EVENT_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+thread_local! {
+    /// Lua states of the event plugins by plugin key and target, the script runs once and its globals are kept between the callbacks.
+    static EVENT_STATES: RefCell<HashMap<(String, usize), Lua>> = RefCell::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PluginEvent {
+    Open,
+    BeforeSave,
+    Save,
+    SelectionChange,
+    ToolUse,
+}
+
+impl PluginEvent {
+    const ALL: [PluginEvent; 5] = [
+        PluginEvent::Open,
+        PluginEvent::BeforeSave,
+        PluginEvent::Save,
+        PluginEvent::SelectionChange,
+        PluginEvent::ToolUse,
+    ];
+
+    pub fn get_function_name(&self) -> &'static str {
+        match self {
+            PluginEvent::Open => "on_open",
+            PluginEvent::BeforeSave => "on_before_save",
+            PluginEvent::Save => "on_save",
+            PluginEvent::SelectionChange => "on_selection_change",
+            PluginEvent::ToolUse => "on_tool_use",
+        }
+    }
+
+    /// A plugin registers a callback by defining a global function with the event name.
+    /// This finds the candidates, if the callback really is a global function is checked after the script ran.
+    fn parse(text: &str) -> anyhow::Result<Vec<PluginEvent>> {
+        let mut result = Vec::new();
+        for event in PluginEvent::ALL {
+            let name = event.get_function_name();
+            let re = Regex::new(&format!(
+                r#"\bfunction\s+(_G\s*\.\s*)?{name}\s*\(|\b{name}\s*=[^=]|_G\s*\[\s*["']{name}["']\s*\]\s*=[^=]"#
+            ))?;
+            if re.is_match(text) {
+                result.push(event);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Drops the plugin states of a target, called when its document gets closed.
+    pub fn clear_states(target: &EditTarget) {
+        let id = target.get_id();
+        EVENT_STATES.with(|states| states.borrow_mut().retain(|(_, target_id), _| *target_id != id));
+    }
+
+    pub fn has_callbacks(&self) -> bool {
+        unsafe { PLUGINS.iter().any(|p| p.events.contains(self)) }
+    }
+
+    /// Calls the callbacks of all plugins for this event, stops at the first plugin that fails or vetoes.
+    pub fn fire(&self, target: &EditTarget, arg: Option<String>) -> anyhow::Result<()> {
+        let plugins: Vec<Plugin> = unsafe { PLUGINS.iter().filter(|p| p.events.contains(self)).cloned().collect() };
+        for plugin in plugins {
+            match plugin.fire_event(target.clone(), *self, arg.clone()) {
+                Ok(None) => {}
+                Ok(Some(reason)) => {
+                    return Err(anyhow::anyhow!(
+                        "{}",
+                        fl!(crate::LANGUAGE_LOADER, "plugin-event-vetoed", title = plugin.title.clone(), reason = reason)
+                    ));
+                }
+                Err(err) => return Err(anyhow::anyhow!("Error in plugin {}: {err}", plugin.title)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
 pub struct Plugin {
     pub title: String,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    /// Submenu path in the plugins menu, from the Category header or the subdirectory.
+    pub category: Vec<String>,
+    pub shortcut: Option<(egui::Key, Modifiers)>,
+    pub path: PathBuf,
     pub text: String,
+    pub parameters: Vec<PluginParameter>,
+    /// Events the plugin has callbacks for.
+    pub events: Vec<PluginEvent>,
+    key: String,
 }
 
 impl Plugin {
     pub fn load(path: &Path) -> anyhow::Result<Self> {
         let text = fs::read_to_string(path)?;
 
-        let re = Regex::new(r"--\s*Title:\s*(.*)")?;
+        let Some(title) = get_header(&text, "Title")? else {
+            return Err(anyhow::anyhow!("No plugin file"));
+        };
+        let parameters = PluginParameter::parse_header(&text)?;
+        let events = PluginEvent::parse(&text)?;
+        let category = get_header(&text, "Category")?
+            .map(|category| category.split('/').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect())
+            .unwrap_or_default();
+        let shortcut = match get_header(&text, "Shortcut")? {
+            Some(shortcut) => Some(parse_shortcut(&shortcut).ok_or_else(|| anyhow::anyhow!("Invalid shortcut '{shortcut}'"))?),
+            None => None,
+        };
+
+        Ok(Self {
+            title,
+            description: get_header(&text, "Description")?,
+            author: get_header(&text, "Author")?,
+            category,
+            shortcut,
+            path: path.to_path_buf(),
+            text,
+            parameters,
+            events,
+            key: path.to_string_lossy().to_string(),
+        })
+    }
 
-        if let Some(cap) = re.captures(&text) {
-            let title = cap.get(1).unwrap().as_str().to_string();
-            return Ok(Self { title, text });
+    /// Loads a plugin from the plugin directory, the key and the default category are relative to it.
+    fn load_from_directory(root: &Path, path: &Path) -> anyhow::Result<Self> {
+        let mut plugin = Plugin::load(path)?;
+        if let Ok(relative_path) = path.strip_prefix(root) {
+            let components: Vec<String> = relative_path.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+            if plugin.category.is_empty() {
+                plugin.category = components[..components.len().saturating_sub(1)].to_vec();
+            }
+            plugin.key = components.join("/");
         }
-        Err(anyhow::anyhow!("No plugin file"))
+        Ok(plugin)
     }
 
-    pub(crate) fn run_plugin(&self, _window: &mut crate::MainWindow<'_>, editor: &crate::AnsiEditor) -> anyhow::Result<()> {
-        let lua = Lua::new();
-        let globals = lua.globals();
+    /// Finds a plugin by title or file name in the plugin directory, or loads it from the given path.
+    pub fn find(name: &str) -> anyhow::Result<Self> {
+        let path = Path::new(name);
+        if path.is_file() {
+            return Plugin::load(path);
+        }
+        unsafe {
+            for p in PLUGINS.iter() {
+                let stem_matches = p
+                    .path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().eq_ignore_ascii_case(name))
+                    .unwrap_or(false);
+                if p.title.eq_ignore_ascii_case(name) || stem_matches {
+                    return Ok(p.clone());
+                }
+            }
+        }
+        Err(anyhow::anyhow!("Plugin '{name}' not found"))
+    }
 
-        globals.set(
-            "log",
-            lua.create_function(move |_lua, txt: String| {
-                log::info!("{txt}");
-                Ok(())
-            })?,
-        )?;
+    pub fn get_key(&self) -> String {
+        self.key.clone()
+    }
 
-        globals.set(
-            "buf",
-            LuaBufferView {
-                buffer_view: editor.buffer_view.clone(),
-            },
-        )?;
+    /// Name used to bind the plugin in the key bindings.
+    pub fn get_binding_name(&self) -> String {
+        format!("plugin:{}", self.key)
+    }
 
-        let sel = editor.buffer_view.lock().get_selection();
+    pub fn get_tooltip(&self) -> Option<String> {
+        let author = self
+            .author
+            .as_ref()
+            .map(|author| fl!(crate::LANGUAGE_LOADER, "plugin-author", author = author.clone()));
+        match (&self.description, author) {
+            (Some(description), Some(author)) => Some(format!("{description}\n\n{author}")),
+            (Some(description), None) => Some(description.clone()),
+            (None, author) => author,
+        }
+    }
 
-        let rect = if let Some(l) = editor.buffer_view.lock().get_edit_state().get_cur_layer() {
-            l.get_rectangle()
-        } else {
-            return Err(anyhow::anyhow!("No layer selected"));
-        };
+    /// Returns the last used parameter values, or the defaults for parameters that weren't set yet.
+    pub fn get_parameter_values(&self) -> Vec<(String, PluginValue)> {
+        let stored = unsafe { PLUGIN_PARAMETERS.get_values(&self.get_key()) };
+        self.parameters
+            .iter()
+            .map(|p| {
+                let value = stored
+                    .and_then(|values| values.iter().find(|(name, _)| *name == p.name))
+                    .map(|(_, value)| value.clone())
+                    .filter(|value| p.is_valid(value))
+                    .unwrap_or_else(|| p.default.clone());
+                (p.name.clone(), value)
+            })
+            .collect()
+    }
 
-        if let Some(sel) = sel {
-            let mut selected_rect = sel.as_rectangle().intersect(&rect);
-            selected_rect -= rect.start;
+    /// Runs the plugin in a background thread on the given editor, the UI stays responsive and can cancel it.
+    /// The progress dialog blocks editor input until the thread finished, so the rollback only undoes the plugin's changes.
+    pub(crate) fn start(&self, editor: &crate::AnsiEditor, control: ExecutionControl) -> anyhow::Result<JoinHandle<anyhow::Result<()>>> {
+        let plugin = self.clone();
+        let target = EditTarget::View(editor.buffer_view.clone());
+        let parameter_values = self.get_parameter_values();
+        let handle = thread::Builder::new()
+            .name("Plugin".into())
+            .spawn(move || plugin.run(target, &parameter_values, &control))?;
+        Ok(handle)
+    }
 
-            globals.set("start_x", selected_rect.left())?;
-            globals.set("end_x", selected_rect.right() - 1)?;
-            globals.set("start_y", selected_rect.top())?;
-            globals.set("end_y", selected_rect.bottom() - 1)?;
-        } else {
-            globals.set("start_x", 0)?;
-            globals.set("end_x", rect.get_width())?;
-            globals.set("start_y", 0)?;
-            globals.set("end_y", rect.get_height())?;
+    /// Runs the plugin as one undo step. If it fails or gets cancelled the changes are rolled back.
+    pub fn run(&self, target: EditTarget, parameter_values: &[(String, PluginValue)], control: &ExecutionControl) -> anyhow::Result<()> {
+        let description = fl!(crate::LANGUAGE_LOADER, "undo-plugin", title = self.title.clone());
+        run_atomic(&target, description, || self.execute(target.clone(), parameter_values, control))
+    }
+
+    fn execute(&self, target: EditTarget, parameter_values: &[(String, PluginValue)], control: &ExecutionControl) -> anyhow::Result<()> {
+        let lua = Lua::new();
+        setup_lua(&lua, target, control)?;
+        set_parameter_globals(&lua, parameter_values.iter().map(|(name, value)| (name.as_str(), value)))?;
+
+        let chunk_name = self.get_chunk_name();
+        if let Err(err) = lua.load(&self.text).set_name(format!("={chunk_name}")).exec() {
+            return Err(anyhow::anyhow!("{}", format_lua_error(&err, &chunk_name)));
         }
-        let _undo = editor
-            .buffer_view
-            .lock()
-            .get_edit_state_mut()
-            .begin_atomic_undo(fl!(crate::LANGUAGE_LOADER, "undo-plugin", title = self.title.clone()));
-        lua.load(&self.text).exec()?;
         Ok(())
     }
 
-    pub fn read_plugin_directory() {
+    /// Calls the callback of the plugin for the event as one undo step.
+    /// The script runs on the first event for a target, the following events only call the callback.
+    /// Returns the reason if the callback vetoes by returning false.
+    pub fn fire_event(&self, target: EditTarget, event: PluginEvent, arg: Option<String>) -> anyhow::Result<Option<String>> {
+        let description = fl!(crate::LANGUAGE_LOADER, "undo-plugin", title = self.title.clone());
+        let state_key = (self.key.clone(), target.get_id());
+        // taken out while the callback runs, so callbacks can't run into a borrowed cache
+        let cached_lua = EVENT_STATES.with(|states| states.borrow_mut().remove(&state_key));
+        let mut loaded_lua = None;
+        let result = run_atomic(&target, description, || {
+            let chunk_name = self.get_chunk_name();
+            let lua_error = |err: mlua::Error| anyhow::anyhow!("{}", format_lua_error(&err, &chunk_name));
+            let lua = match cached_lua {
+                Some(lua) => {
+                    update_lua(&lua, target.clone(), &ExecutionControl::new(Some(PLUGIN_EVENT_TIME_LIMIT)))?;
+                    lua
+                }
+                None => {
+                    let lua = Lua::new();
+                    setup_lua(&lua, target.clone(), &ExecutionControl::new(Some(PLUGIN_EVENT_TIME_LIMIT)))?;
+                    lua.load(&self.text).set_name(format!("={chunk_name}")).exec().map_err(lua_error)?;
+                    lua
+                }
+            };
+            let lua = loaded_lua.insert(lua);
+            let mlua::Value::Function(callback) = lua.globals().get::<_, mlua::Value>(event.get_function_name())? else {
+                return Ok(None);
+            };
+            let (result, reason): (Option<bool>, Option<String>) = callback.call(arg).map_err(lua_error)?;
+            if result == Some(false) {
+                Ok(Some(reason.unwrap_or_default()))
+            } else {
+                Ok(None)
+            }
+        });
+        if let Some(lua) = loaded_lua {
+            EVENT_STATES.with(|states| states.borrow_mut().insert(state_key, lua));
+        }
+        result
+    }
+
+    fn get_chunk_name(&self) -> String {
+        match self.path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => self.title.clone(),
+        }
+    }
+
+    /// (Re)loads all plugins into PLUGINS and returns the errors of the plugins that failed to load.
+    pub fn read_plugin_directory() -> Vec<String> {
         let Ok(root) = Settings::get_plugin_directory() else {
             log::error!("Can't read plugin directory.");
-            return;
+            return Vec::new();
         };
-        let walker = WalkDir::new(root).into_iter();
-        for entry in walker.filter_entry(|e| !FontTool::is_hidden(e)) {
-            match entry {
-                Ok(entry) => {
-                    if entry.file_type().is_dir() {
-                        continue;
-                    }
-                    unsafe {
-                        match Plugin::load(entry.path()) {
-                            Ok(plugin) => {
-                                PLUGINS.push(plugin);
-                            }
-                            Err(err) => log::error!("Error loading plugin: {err}"),
-                        }
+        let (plugins, errors) = read_script_directory(&root, |path| Plugin::load_from_directory(&root, path));
+        unsafe {
+            PLUGINS = plugins;
+        }
+        EVENT_STATES.with(|states| states.borrow_mut().clear());
+        errors
+    }
+
+    /// Watches the plugin directory, changes are picked up with `plugin_directory_changed`.
+    pub fn install_watcher(ctx: egui::Context) {
+        if let Ok(root) = Settings::get_plugin_directory() {
+            thread::spawn(move || {
+                if let Err(err) = watch(root.as_path(), &ctx) {
+                    log::error!("Can't watch plugin directory: {err}");
+                }
+            });
+        }
+    }
+
+    pub fn plugin_directory_changed() -> bool {
+        PLUGIN_DIRECTORY_CHANGED.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Runs `f` as one undo step, the changes get rolled back if it fails.
+pub fn run_atomic<T>(target: &EditTarget, description: String, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let undo_len = target.lock().undo_stack_len();
+    let result = {
+        let _undo = target.lock().begin_atomic_undo(description);
+        f()
+    };
+    if result.is_err() && target.lock().undo_stack_len() > undo_len {
+        if let Err(err) = target.lock().undo() {
+            log::error!("Error rolling back changes: {err}");
+        }
+    }
+    result
+}
+
+/// Sets up the environment plugins run in: the `buf` userdata, the selection bounds, `log`, `set_progress`
+/// and a hook that aborts the script when it gets cancelled or exceeds the time limit.
+pub fn setup_lua(lua: &Lua, target: EditTarget, control: &ExecutionControl) -> anyhow::Result<()> {
+    let globals = lua.globals();
+
+    let progress = control.progress.clone();
+    globals.set(
+        "set_progress",
+        lua.create_function(move |_lua, (value, max): (f32, Option<f32>)| {
+            let value = match max {
+                Some(max) if max > 0.0 => value / max,
+                _ => value,
+            };
+            *progress.lock() = Some(value.clamp(0.0, 1.0));
+            Ok(())
+        })?,
+    )?;
+
+    globals.set(
+        "get_tdf_fonts",
+        lua.create_function(|_lua, ()| {
+            let names: Vec<String> = FontTool::get_fonts().lock().iter().map(|f| f.name.clone()).collect();
+            Ok(names)
+        })?,
+    )?;
+
+    globals.set(
+        "log",
+        lua.create_function(move |_lua, txt: String| {
+            log::info!("{txt}");
+            Ok(())
+        })?,
+    )?;
+
+    update_lua(lua, target, control)
+}
+
+/// Prepares a set up state for the next run: points `buf` to the target, updates the selection bounds
+/// and restarts the time limit. Globals defined by the script are kept.
+pub fn update_lua(lua: &Lua, target: EditTarget, control: &ExecutionControl) -> anyhow::Result<()> {
+    let globals = lua.globals();
+
+    let cancel = control.cancel.clone();
+    let time_limit = control.time_limit;
+    let start_time = Instant::now();
+    lua.set_hook(HookTriggers::new().every_nth_instruction(1000), move |_lua, _debug| {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(mlua::Error::RuntimeError("Plugin cancelled".to_string()));
+        }
+        if let Some(time_limit) = time_limit {
+            if start_time.elapsed() > time_limit {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "Plugin exceeded the time limit of {}s",
+                    time_limit.as_secs()
+                )));
+            }
+        }
+        Ok(())
+    });
+
+    let sel = target.lock().get_selection();
+
+    let rect = if let Some(l) = target.lock().get_cur_layer() {
+        l.get_rectangle()
+    } else {
+        return Err(anyhow::anyhow!("No layer selected"));
+    };
+
+    if let Some(sel) = sel {
+        let mut selected_rect = sel.as_rectangle().intersect(&rect);
+        selected_rect -= rect.start;
+
+        globals.set("start_x", selected_rect.left())?;
+        globals.set("end_x", selected_rect.right() - 1)?;
+        globals.set("start_y", selected_rect.top())?;
+        globals.set("end_y", selected_rect.bottom() - 1)?;
+    } else {
+        globals.set("start_x", 0)?;
+        globals.set("end_x", rect.get_width())?;
+        globals.set("start_y", 0)?;
+        globals.set("end_y", rect.get_height())?;
+    }
+    globals.set("buf", LuaBufferView { target })?;
+
+    Ok(())
+}
+
+/// Sets the parameter values of a plugin or tool as globals.
+pub fn set_parameter_globals<'a>(lua: &Lua, values: impl IntoIterator<Item = (&'a str, &'a PluginValue)>) -> mlua::Result<()> {
+    let globals = lua.globals();
+    for (name, value) in values {
+        match value {
+            PluginValue::Int(i) => globals.set(name, *i)?,
+            PluginValue::Float(f) => globals.set(name, *f)?,
+            PluginValue::Bool(b) => globals.set(name, *b)?,
+            PluginValue::String(s) => globals.set(name, s.as_str())?,
+        }
+    }
+    Ok(())
+}
+
+/// Loads all scripts below the directory, skipping hidden files. Returns the loaded scripts together with the errors
+/// of the scripts that failed to load.
+pub fn read_script_directory<T>(root: &Path, mut load: impl FnMut(&Path) -> anyhow::Result<T>) -> (Vec<T>, Vec<String>) {
+    let mut scripts = Vec::new();
+    let mut errors = Vec::new();
+    for entry in WalkDir::new(root).sort_by_file_name().into_iter().filter_entry(|e| !FontTool::is_hidden(e)) {
+        match entry {
+            Ok(entry) => {
+                if entry.file_type().is_dir() {
+                    continue;
+                }
+                match load(entry.path()) {
+                    Ok(script) => scripts.push(script),
+                    Err(err) => {
+                        let file_name = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().to_string();
+                        log::error!("Error loading {file_name}: {err}");
+                        errors.push(format!("{file_name}: {err}"));
                     }
                 }
-                Err(err) => log::error!("Error loading plugin: {err}"),
             }
+            Err(err) => {
+                log::error!("Error loading script: {err}");
+                errors.push(err.to_string());
+            }
+        }
+    }
+    (scripts, errors)
+}
+
+static PLUGIN_DIRECTORY_CHANGED: AtomicBool = AtomicBool::new(false);
+
+fn watch(path: &Path, ctx: &egui::Context) -> notify::Result<()> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
+    watcher.watch(path, RecursiveMode::Recursive)?;
+
+    while let Ok(res) = rx.recv() {
+        match res {
+            Ok(_) => {
+                // editors write files in several steps, wait until things settle down
+                thread::sleep(Duration::from_millis(250));
+                while rx.try_recv().is_ok() {}
+                PLUGIN_DIRECTORY_CHANGED.store(true, Ordering::Relaxed);
+                ctx.request_repaint();
+            }
+            Err(e) => log::error!("watch error: {e:}"),
+        }
+    }
+    Ok(())
+}
+
+pub fn get_header(text: &str, name: &str) -> anyhow::Result<Option<String>> {
+    let re = Regex::new(&format!(r"--\s*{name}:\s*(.*)"))?;
+    Ok(re
+        .captures(text)
+        .map(|cap| cap.get(1).unwrap().as_str().trim().to_string())
+        .filter(|value| !value.is_empty()))
+}
+
+/// Parses shortcuts like "Ctrl+Shift+E".
+pub fn parse_shortcut(shortcut: &str) -> Option<(egui::Key, Modifiers)> {
+    let mut modifiers = Modifiers::NONE;
+    let mut key = None;
+    for part in shortcut.split('+').map(|p| p.trim()) {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers.ctrl = true,
+            "alt" => modifiers.alt = true,
+            "shift" => modifiers.shift = true,
+            _ => {
+                if key.is_some() {
+                    return None;
+                }
+                key = Some(key_from_name(part)?);
+            }
+        }
+    }
+    key.map(|key| (key, modifiers))
+}
+
+/// Looks up a key by its egui name ("A", "F5", "Enter", "Up", "Plus", …), ignoring case.
+fn key_from_name(name: &str) -> Option<egui::Key> {
+    use egui::Key::*;
+    const KEYS: &[egui::Key] = &[
+        ArrowDown, ArrowLeft, ArrowRight, ArrowUp, Escape, Tab, Backspace, Enter, Space, Insert, Delete, Home, End, PageUp, PageDown, Minus, PlusEquals, Num0,
+        Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, F1, F2, F3, F4, F5,
+        F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20,
+    ];
+    KEYS.iter().copied().find(|key| key.name().eq_ignore_ascii_case(name))
+}
+
+/// The plugins menu, submenus are built from the plugin categories.
+#[derive(Default)]
+pub struct PluginMenu {
+    pub title: String,
+    pub submenus: Vec<PluginMenu>,
+    /// Indices into PLUGINS
+    pub plugins: Vec<usize>,
+}
+
+impl PluginMenu {
+    pub fn build(plugins: &[Plugin]) -> Self {
+        let mut root = PluginMenu::default();
+        for (i, plugin) in plugins.iter().enumerate() {
+            let mut menu = &mut root;
+            for title in &plugin.category {
+                let idx = if let Some(idx) = menu.submenus.iter().position(|m| m.title == *title) {
+                    idx
+                } else {
+                    menu.submenus.push(PluginMenu {
+                        title: title.clone(),
+                        ..Default::default()
+                    });
+                    menu.submenus.len() - 1
+                };
+                menu = &mut menu.submenus[idx];
+            }
+            menu.plugins.push(i);
+        }
+        root.sort(plugins);
+        root
+    }
+
+    fn sort(&mut self, plugins: &[Plugin]) {
+        self.submenus.sort_by_key(|m| m.title.to_lowercase());
+        self.plugins.sort_by_key(|i| plugins[*i].title.to_lowercase());
+        for submenu in &mut self.submenus {
+            submenu.sort(plugins);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ExecutionControl {
+    pub cancel: Arc<AtomicBool>,
+    pub progress: Arc<Mutex<Option<f32>>>,
+    pub time_limit: Option<Duration>,
+}
+
+impl ExecutionControl {
+    pub fn new(time_limit: Option<Duration>) -> Self {
+        Self {
+            cancel: Arc::new(AtomicBool::new(false)),
+            progress: Arc::new(Mutex::new(None)),
+            time_limit,
+        }
+    }
+}
+
+/// Reduces a lua error to a single line with the script position, callback errors only have it in the traceback.
+pub fn format_lua_error(err: &mlua::Error, chunk_name: &str) -> String {
+    match err {
+        mlua::Error::CallbackError { traceback, cause } => {
+            let prefix = format!("{chunk_name}:");
+            let location = traceback
+                .lines()
+                .map(str::trim)
+                .find(|line| line.starts_with(&prefix))
+                .and_then(|line| line.split(": in").next());
+            let cause = format_lua_error(cause, chunk_name);
+            match location {
+                Some(location) if !cause.starts_with(&prefix) => format!("{location}: {cause}"),
+                _ => cause,
+            }
+        }
+        mlua::Error::SyntaxError { message, .. } => message.clone(),
+        mlua::Error::RuntimeError(message) => message.lines().next().unwrap_or_default().to_string(),
+        _ => err.to_string(),
+    }
+}
+
+/// The edit state a plugin works on - either the one of an open editor or a standalone one for headless runs.
+#[derive(Clone)]
+pub enum EditTarget {
+    View(Arc<Mutex<BufferView>>),
+    Headless(Arc<Mutex<EditState>>),
+}
+
+impl EditTarget {
+    /// Identifies the edit state, clones of a target have the same id.
+    pub fn get_id(&self) -> usize {
+        match self {
+            EditTarget::View(buffer_view) => Arc::as_ptr(buffer_view) as usize,
+            EditTarget::Headless(state) => Arc::as_ptr(state) as usize,
+        }
+    }
+
+    pub fn lock(&self) -> EditStateGuard<'_> {
+        match self {
+            EditTarget::View(buffer_view) => EditStateGuard::View(buffer_view.lock()),
+            EditTarget::Headless(state) => EditStateGuard::Headless(state.lock()),
+        }
+    }
+}
+
+pub enum EditStateGuard<'a> {
+    View(MutexGuard<'a, BufferView>),
+    Headless(MutexGuard<'a, EditState>),
+}
+
+impl Deref for EditStateGuard<'_> {
+    type Target = EditState;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            EditStateGuard::View(buffer_view) => buffer_view.get_edit_state(),
+            EditStateGuard::Headless(state) => &**state,
+        }
+    }
+}
+
+impl DerefMut for EditStateGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            EditStateGuard::View(buffer_view) => buffer_view.get_edit_state_mut(),
+            EditStateGuard::Headless(state) => &mut **state,
         }
     }
 }
 
 struct LuaBufferView {
-    buffer_view: Arc<eframe::epaint::mutex::Mutex<icy_engine_egui::BufferView>>,
+    target: EditTarget,
 }
 
 impl LuaBufferView {
+    fn get_sauce_data(&self) -> SauceData {
+        self.target.lock().get_buffer().get_sauce().clone().unwrap_or_default()
+    }
+
+    fn get_palette_colors(&self) -> Vec<Color> {
+        self.target.lock().get_buffer().palette.color_iter().cloned().collect()
+    }
+
+    fn check_palette_index(&self, idx: usize) -> mlua::Result<()> {
+        let palette_len = self.target.lock().get_buffer().palette.len();
+        if idx < palette_len {
+            Ok(())
+        } else {
+            Err(mlua::Error::SyntaxError {
+                message: format!("Color {} out of range (0..<{})", idx, palette_len),
+                incomplete_input: false,
+            })
+        }
+    }
+
+    /// Replaces the palette as one undo operation.
+    fn switch_palette(&self, colors: &[Color]) -> mlua::Result<()> {
+        let mut state = self.target.lock();
+        let mut palette = Palette::from_slice(colors);
+        palette.description = state.get_buffer().palette.description.clone();
+        state.switch_to_palette(palette).map_err(engine_error)
+    }
+
+    /// Renders a text with a TheDraw font at the given layer position, the same way the font tool does.
+    fn render_tdf(&self, font: &TheDrawFont, text: &str, pos: Position, outline_style: usize) -> mlua::Result<(i32, i32)> {
+        let mut width = 0;
+        let mut height = 0;
+        for ch in text.chars() {
+            // tdf fonts are CP437
+            let ch = icy_engine::ascii::CP437Converter::default().convert_from_unicode(ch, 0);
+            let x = pos.x + width;
+            let mut state = self.target.lock();
+            state.set_outline_style(outline_style);
+            state.get_caret_mut().set_x_position(x);
+            state.get_caret_mut().set_y_position(pos.y);
+            if let Some(size) = font.render(&mut state, ch as u8) {
+                width += size.width + font.spaces;
+                height = height.max(size.height);
+            } else {
+                let mut attr = state.get_caret().get_attribute();
+                attr.attr &= !attribute::INVISIBLE;
+                state.set_char((x, pos.y), AttributedChar::new(ch, attr)).map_err(engine_error)?;
+                width += 1;
+                height = height.max(1);
+            }
+        }
+        self.target.lock().get_caret_mut().set_x_position(pos.x + width);
+        Ok((width, height))
+    }
+
+    /// Runs f with the caret on the given font page, bit font operations work on the caret font page.
+    fn with_font_page<R>(&self, page: usize, f: impl FnOnce(&mut EditState) -> R) -> R {
+        let mut state = self.target.lock();
+        let cur_page = state.get_caret().get_font_page();
+        state.get_caret_mut().set_font_page(page);
+        let result = f(&mut state);
+        state.get_caret_mut().set_font_page(cur_page);
+        result
+    }
+
+    fn check_layer(&self, layer: usize) -> mlua::Result<()> {
+        let layer_len = self.target.lock().get_buffer().layers.len();
+        if layer < layer_len {
+            Ok(())
+        } else {
+            Err(mlua::Error::SyntaxError {
+                message: format!("Layer {} out of range (0..<{})", layer, layer_len),
+                incomplete_input: false,
+            })
+        }
+    }
+
+    fn get_layer_offset(&self) -> Position {
+        self.target.lock().get_cur_layer().map(|layer| layer.get_offset()).unwrap_or_default()
+    }
+
+    /// Adds (or removes) a rectangle in current layer coordinates to the selection mask.
+    fn select_rectangle(&self, x: i32, y: i32, width: i32, height: i32, selected: bool) -> mlua::Result<()> {
+        let offset = self.get_layer_offset();
+        let rect = Rectangle::from(x + offset.x, y + offset.y, width, height);
+        let mut state = self.target.lock();
+        state.add_selection_to_mask().map_err(engine_error)?;
+        state.deselect().map_err(engine_error)?;
+        state.enumerate_selections(|pos, _, _| {
+            if pos.x >= rect.left() && pos.x < rect.right() && pos.y >= rect.top() && pos.y < rect.bottom() {
+                Some(selected)
+            } else {
+                None
+            }
+        });
+        Ok(())
+    }
+
+    /// Runs an edit state operation that works on the current layer on the given layer instead.
+    fn with_layer<R>(&self, layer: usize, f: impl FnOnce(&mut EditState) -> R) -> mlua::Result<R> {
+        self.check_layer(layer)?;
+        let mut state = self.target.lock();
+        let cur_layer = state.get_current_layer().unwrap_or(0);
+        state.set_current_layer(layer);
+        let result = f(&mut state);
+        let layer_len = state.get_buffer().layers.len();
+        state.set_current_layer(cur_layer.min(layer_len.saturating_sub(1)));
+        Ok(result)
+    }
+
+    /// Changes title, mode or alpha channel of a layer as undoable operation.
+    fn update_layer_properties(&self, layer: usize, f: impl FnOnce(&mut LayerProperties)) -> mlua::Result<()> {
+        self.check_layer(layer)?;
+        let mut state = self.target.lock();
+        let old_properties = LayerProperties::from_layer(&state.get_buffer().layers[layer]);
+        let mut new_properties = old_properties.clone();
+        f(&mut new_properties);
+        state
+            .push_undo_action(Box::new(UpdateLayerProperties::new(layer, old_properties, new_properties)))
+            .map_err(engine_error)
+    }
+
     fn convert_from_unicode(&self, ch: String) -> mlua::Result<char> {
         let Some(ch) = ch.chars().next() else {
             return Err(mlua::Error::SyntaxError {
@@ -116,128 +815,192 @@ impl LuaBufferView {
             });
         };
 
-        let buffer_type = self.buffer_view.lock().get_buffer().buffer_type;
+        let buffer_type = self.target.lock().get_buffer().buffer_type;
         let ch = match buffer_type {
             icy_engine::BufferType::Unicode => ch,
             icy_engine::BufferType::CP437 => {
-                icy_engine::ascii::CP437Converter::default().convert_from_unicode(ch, self.buffer_view.lock().get_caret().get_font_page())
+                icy_engine::ascii::CP437Converter::default().convert_from_unicode(ch, self.target.lock().get_caret().get_font_page())
             }
             icy_engine::BufferType::Petscii => {
-                icy_engine::petscii::CharConverter::default().convert_from_unicode(ch, self.buffer_view.lock().get_caret().get_font_page())
+                icy_engine::petscii::CharConverter::default().convert_from_unicode(ch, self.target.lock().get_caret().get_font_page())
             }
             icy_engine::BufferType::Atascii => {
-                icy_engine::atascii::CharConverter::default().convert_from_unicode(ch, self.buffer_view.lock().get_caret().get_font_page())
+                icy_engine::atascii::CharConverter::default().convert_from_unicode(ch, self.target.lock().get_caret().get_font_page())
             }
             icy_engine::BufferType::Viewdata => {
-                icy_engine::viewdata::CharConverter::default().convert_from_unicode(ch, self.buffer_view.lock().get_caret().get_font_page())
+                icy_engine::viewdata::CharConverter::default().convert_from_unicode(ch, self.target.lock().get_caret().get_font_page())
             }
         };
         Ok(ch)
     }
 
     fn convert_to_unicode(&self, ch: AttributedChar) -> String {
-        let buffer_type = self.buffer_view.lock().get_buffer().buffer_type;
-        let ch = match buffer_type {
-            icy_engine::BufferType::Unicode => ch.ch,
-            icy_engine::BufferType::CP437 => icy_engine::ascii::CP437Converter::default().convert_to_unicode(ch),
-            icy_engine::BufferType::Petscii => icy_engine::petscii::CharConverter::default().convert_to_unicode(ch),
-            icy_engine::BufferType::Atascii => icy_engine::atascii::CharConverter::default().convert_to_unicode(ch),
-            icy_engine::BufferType::Viewdata => icy_engine::viewdata::CharConverter::default().convert_to_unicode(ch),
-        };
-        ch.to_string()
+        let buffer_type = self.target.lock().get_buffer().buffer_type;
+        convert_to_unicode(buffer_type, ch).to_string()
     }
 }
 
 impl UserData for LuaBufferView {
     fn add_fields<'lua, F: mlua::UserDataFields<'lua, Self>>(fields: &mut F) {
-        fields.add_field_method_get("height", |_, this| Ok(this.buffer_view.lock().get_buffer_mut().get_height()));
+        fields.add_field_method_get("height", |_, this| Ok(this.target.lock().get_buffer_mut().get_height()));
         fields.add_field_method_set("height", |_, this, val| {
-            this.buffer_view.lock().get_buffer_mut().set_height(val);
+            this.target.lock().get_buffer_mut().set_height(val);
             Ok(())
         });
-        fields.add_field_method_get("width", |_, this| Ok(this.buffer_view.lock().get_buffer_mut().get_width()));
+        fields.add_field_method_get("width", |_, this| Ok(this.target.lock().get_buffer_mut().get_width()));
         fields.add_field_method_set("width", |_, this, val| {
-            this.buffer_view.lock().get_buffer_mut().set_width(val);
+            this.target.lock().get_buffer_mut().set_width(val);
             Ok(())
         });
 
-        fields.add_field_method_get("font_page", |_, this| Ok(this.buffer_view.lock().get_caret_mut().get_font_page()));
+        fields.add_field_method_get("font_page", |_, this| Ok(this.target.lock().get_caret_mut().get_font_page()));
         fields.add_field_method_set("font_page", |_, this, val| {
-            this.buffer_view.lock().get_caret_mut().set_font_page(val);
+            this.target.lock().get_caret_mut().set_font_page(val);
             Ok(())
         });
 
-        fields.add_field_method_get("layer", |_, this| Ok(this.buffer_view.lock().get_edit_state_mut().get_current_layer().unwrap()));
+        fields.add_field_method_get("layer", |_, this| Ok(this.target.lock().get_current_layer().unwrap()));
         fields.add_field_method_set("layer", |_, this, val| {
-            if val < this.buffer_view.lock().get_buffer_mut().layers.len() {
-                this.buffer_view.lock().get_edit_state_mut().set_current_layer(val);
+            if val < this.target.lock().get_buffer_mut().layers.len() {
+                this.target.lock().set_current_layer(val);
                 Ok(())
             } else {
                 Err(mlua::Error::SyntaxError {
-                    message: format!("Layer {} out of range (0..<{})", val, this.buffer_view.lock().get_buffer_mut().layers.len()),
+                    message: format!("Layer {} out of range (0..<{})", val, this.target.lock().get_buffer_mut().layers.len()),
                     incomplete_input: false,
                 })
             }
         });
 
-        fields.add_field_method_get("fg", |_, this| Ok(this.buffer_view.lock().get_caret_mut().get_attribute().get_foreground()));
+        fields.add_field_method_get("fg", |_, this| Ok(this.target.lock().get_caret_mut().get_attribute().get_foreground()));
         fields.add_field_method_set("fg", |_, this, val| {
-            let mut attr = this.buffer_view.lock().get_caret_mut().get_attribute();
+            let mut attr = this.target.lock().get_caret_mut().get_attribute();
             attr.set_foreground(val);
-            this.buffer_view.lock().get_caret_mut().set_attr(attr);
+            this.target.lock().get_caret_mut().set_attr(attr);
             Ok(())
         });
 
-        fields.add_field_method_get("bg", |_, this| Ok(this.buffer_view.lock().get_caret_mut().get_attribute().get_background()));
+        fields.add_field_method_get("bg", |_, this| Ok(this.target.lock().get_caret_mut().get_attribute().get_background()));
         fields.add_field_method_set("bg", |_, this, val| {
-            let mut attr = this.buffer_view.lock().get_caret_mut().get_attribute();
+            let mut attr = this.target.lock().get_caret_mut().get_attribute();
             attr.set_background(val);
-            this.buffer_view.lock().get_caret_mut().set_attr(attr);
+            this.target.lock().get_caret_mut().set_attr(attr);
             Ok(())
         });
 
-        fields.add_field_method_get("x", |_, this| Ok(this.buffer_view.lock().get_caret_mut().get_position().x));
+        fields.add_field_method_get("x", |_, this| Ok(this.target.lock().get_caret_mut().get_position().x));
         fields.add_field_method_set("x", |_, this, val| {
-            this.buffer_view.lock().get_caret_mut().set_x_position(val);
+            this.target.lock().get_caret_mut().set_x_position(val);
             Ok(())
         });
 
-        fields.add_field_method_get("y", |_, this| Ok(this.buffer_view.lock().get_caret_mut().get_position().y));
+        fields.add_field_method_get("y", |_, this| Ok(this.target.lock().get_caret_mut().get_position().y));
         fields.add_field_method_set("y", |_, this, val| {
-            this.buffer_view.lock().get_caret_mut().set_y_position(val);
+            this.target.lock().get_caret_mut().set_y_position(val);
             Ok(())
         });
 
-        fields.add_field_method_get("layer_count", |_, this| Ok(this.buffer_view.lock().get_buffer_mut().layers.len()));
+        fields.add_field_method_get("layer_count", |_, this| Ok(this.target.lock().get_buffer_mut().layers.len()));
+
+        fields.add_field_method_get("sauce_title", |_, this| Ok(this.get_sauce_data().title.to_string()));
+        fields.add_field_method_set("sauce_title", |_, this, val: String| {
+            let mut sauce = this.get_sauce_data();
+            sauce.title = SauceString::from(&val);
+            this.target.lock().update_sauce_data(Some(sauce)).map_err(engine_error)
+        });
+
+        fields.add_field_method_get("sauce_author", |_, this| Ok(this.get_sauce_data().author.to_string()));
+        fields.add_field_method_set("sauce_author", |_, this, val: String| {
+            let mut sauce = this.get_sauce_data();
+            sauce.author = SauceString::from(&val);
+            this.target.lock().update_sauce_data(Some(sauce)).map_err(engine_error)
+        });
+
+        fields.add_field_method_get("sauce_group", |_, this| Ok(this.get_sauce_data().group.to_string()));
+        fields.add_field_method_set("sauce_group", |_, this, val: String| {
+            let mut sauce = this.get_sauce_data();
+            sauce.group = SauceString::from(&val);
+            this.target.lock().update_sauce_data(Some(sauce)).map_err(engine_error)
+        });
+
+        fields.add_field_method_get("palette_size", |_, this| Ok(this.target.lock().get_buffer().palette.len()));
+
+        fields.add_field_method_get("palette_mode", |_, this| {
+            let mode = match this.target.lock().get_buffer().palette_mode {
+                PaletteMode::RGB => "rgb",
+                PaletteMode::Fixed16 => "fixed16",
+                PaletteMode::Free16 => "free16",
+                PaletteMode::Free8 => "free8",
+            };
+            Ok(mode)
+        });
+        fields.add_field_method_set("palette_mode", |_, this, mode: String| {
+            let mode = match mode.as_str() {
+                "rgb" => PaletteMode::RGB,
+                "fixed16" => PaletteMode::Fixed16,
+                "free16" => PaletteMode::Free16,
+                "free8" => PaletteMode::Free8,
+                _ => {
+                    return Err(mlua::Error::SyntaxError {
+                        message: format!("Unknown palette mode '{mode}' (rgb, fixed16, free16, free8)"),
+                        incomplete_input: false,
+                    })
+                }
+            };
+            this.target.lock().set_palette_mode(mode).map_err(engine_error)
+        });
+
+        fields.add_field_method_get("ice_mode", |_, this| {
+            let mode = match this.target.lock().get_buffer().ice_mode {
+                IceMode::Unlimited => "unlimited",
+                IceMode::Blink => "blink",
+                IceMode::Ice => "ice",
+            };
+            Ok(mode)
+        });
+        fields.add_field_method_set("ice_mode", |_, this, mode: String| {
+            let mode = match mode.as_str() {
+                "unlimited" => IceMode::Unlimited,
+                "blink" => IceMode::Blink,
+                "ice" => IceMode::Ice,
+                _ => {
+                    return Err(mlua::Error::SyntaxError {
+                        message: format!("Unknown ice mode '{mode}' (unlimited, blink, ice)"),
+                        incomplete_input: false,
+                    })
+                }
+            };
+            this.target.lock().set_ice_mode(mode).map_err(engine_error)
+        });
     }
 
     fn add_methods<'lua, M: mlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_method_mut("fg_rgb", |_, this, (r, g, b): (u8, u8, u8)| {
-            let color = this.buffer_view.lock().get_buffer_mut().palette.insert_color_rgb(r, g, b);
-            this.buffer_view.lock().get_caret_mut().set_foreground(color);
+            let color = this.target.lock().get_buffer_mut().palette.insert_color_rgb(r, g, b);
+            this.target.lock().get_caret_mut().set_foreground(color);
             Ok(color)
         });
 
         methods.add_method_mut("bg_rgb", |_, this, (r, g, b): (u8, u8, u8)| {
-            let color = this.buffer_view.lock().get_buffer_mut().palette.insert_color_rgb(r, g, b);
-            this.buffer_view.lock().get_caret_mut().set_background(color);
+            let color = this.target.lock().get_buffer_mut().palette.insert_color_rgb(r, g, b);
+            this.target.lock().get_caret_mut().set_background(color);
             Ok(color)
         });
 
         methods.add_method_mut("set_char", |_, this, (x, y, ch): (i32, i32, String)| {
-            let cur_layer = this.buffer_view.lock().get_edit_state_mut().get_current_layer().unwrap();
-            let layer_len = this.buffer_view.lock().get_buffer_mut().layers.len();
+            let cur_layer = this.target.lock().get_current_layer().unwrap();
+            let layer_len = this.target.lock().get_buffer_mut().layers.len();
             if cur_layer >= layer_len {
                 return Err(mlua::Error::SyntaxError {
                     message: format!("Current layer {} out of range (0..<{})", cur_layer, layer_len),
                     incomplete_input: false,
                 });
             }
-            let mut attr = this.buffer_view.lock().get_caret_mut().get_attribute();
+            let mut attr = this.target.lock().get_caret_mut().get_attribute();
             attr.attr &= !attribute::INVISIBLE;
             let ch = AttributedChar::new(this.convert_from_unicode(ch)?, attr);
 
-            if let Err(err) = this.buffer_view.lock().get_edit_state_mut().set_char((x, y), ch) {
+            if let Err(err) = this.target.lock().set_char((x, y), ch) {
                 return Err(mlua::Error::SyntaxError {
                     message: format!("Error setting char: {}", err),
                     incomplete_input: false,
@@ -247,8 +1010,8 @@ impl UserData for LuaBufferView {
         });
 
         methods.add_method_mut("get_char", |_, this, (x, y): (i32, i32)| {
-            let cur_layer = this.buffer_view.lock().get_edit_state_mut().get_current_layer().unwrap();
-            let layer_len = this.buffer_view.lock().get_buffer_mut().layers.len();
+            let cur_layer = this.target.lock().get_current_layer().unwrap();
+            let layer_len = this.target.lock().get_buffer_mut().layers.len();
             if cur_layer >= layer_len {
                 return Err(mlua::Error::SyntaxError {
                     message: format!("Current layer {} out of range (0..<{})", cur_layer, layer_len),
@@ -256,13 +1019,13 @@ impl UserData for LuaBufferView {
                 });
             }
 
-            let ch = this.buffer_view.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
+            let ch = this.target.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
             Ok(this.convert_to_unicode(ch))
         });
 
         methods.add_method_mut("pickup_char", |_, this, (x, y): (i32, i32)| {
-            let cur_layer = this.buffer_view.lock().get_edit_state_mut().get_current_layer().unwrap();
-            let layer_len = this.buffer_view.lock().get_buffer_mut().layers.len();
+            let cur_layer = this.target.lock().get_current_layer().unwrap();
+            let layer_len = this.target.lock().get_buffer_mut().layers.len();
             if cur_layer >= layer_len {
                 return Err(mlua::Error::SyntaxError {
                     message: format!("Current layer {} out of range (0..<{})", cur_layer, layer_len),
@@ -270,32 +1033,32 @@ impl UserData for LuaBufferView {
                 });
             }
 
-            let ch = this.buffer_view.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
+            let ch = this.target.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
             let mut attr = ch.attribute;
             attr.attr &= !attribute::INVISIBLE;
-            this.buffer_view.lock().get_caret_mut().set_attr(attr);
+            this.target.lock().get_caret_mut().set_attr(attr);
 
             Ok(this.convert_to_unicode(ch))
         });
 
         methods.add_method_mut("set_fg", |_, this, (x, y, col): (i32, i32, u32)| {
-            let cur_layer = this.buffer_view.lock().get_edit_state_mut().get_current_layer().unwrap();
-            let layer_len = this.buffer_view.lock().get_buffer_mut().layers.len();
+            let cur_layer = this.target.lock().get_current_layer().unwrap();
+            let layer_len = this.target.lock().get_buffer_mut().layers.len();
             if cur_layer >= layer_len {
                 return Err(mlua::Error::SyntaxError {
                     message: format!("Current layer {} out of range (0..<{})", cur_layer, layer_len),
                     incomplete_input: false,
                 });
             }
-            let mut ch = this.buffer_view.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
+            let mut ch = this.target.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
             ch.attribute.set_foreground(col);
-            this.buffer_view.lock().get_buffer_mut().layers[cur_layer].set_char((x, y), ch);
+            this.target.lock().get_buffer_mut().layers[cur_layer].set_char((x, y), ch);
             Ok(())
         });
 
         methods.add_method_mut("get_fg", |_, this, (x, y): (i32, i32)| {
-            let cur_layer = this.buffer_view.lock().get_edit_state_mut().get_current_layer().unwrap();
-            let layer_len = this.buffer_view.lock().get_buffer_mut().layers.len();
+            let cur_layer = this.target.lock().get_current_layer().unwrap();
+            let layer_len = this.target.lock().get_buffer_mut().layers.len();
             if cur_layer >= layer_len {
                 return Err(mlua::Error::SyntaxError {
                     message: format!("Current layer {} out of range (0..<{})", cur_layer, layer_len),
@@ -303,107 +1066,640 @@ impl UserData for LuaBufferView {
                 });
             }
 
-            let ch = this.buffer_view.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
+            let ch = this.target.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
             Ok(ch.attribute.get_foreground())
         });
 
         methods.add_method_mut("set_bg", |_, this, (x, y, col): (i32, i32, u32)| {
-            let cur_layer = this.buffer_view.lock().get_edit_state_mut().get_current_layer().unwrap();
-            let layer_len = this.buffer_view.lock().get_buffer_mut().layers.len();
+            let cur_layer = this.target.lock().get_current_layer().unwrap();
+            let layer_len = this.target.lock().get_buffer_mut().layers.len();
             if cur_layer >= layer_len {
                 return Err(mlua::Error::SyntaxError {
                     message: format!("Current layer {} out of range (0..<{})", cur_layer, layer_len),
                     incomplete_input: false,
                 });
             }
-            let mut ch = this.buffer_view.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
+            let mut ch = this.target.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
             ch.attribute.set_background(col);
-            this.buffer_view.lock().get_buffer_mut().layers[cur_layer].set_char((x, y), ch);
+            this.target.lock().get_buffer_mut().layers[cur_layer].set_char((x, y), ch);
             Ok(())
         });
 
         methods.add_method_mut("get_bg", |_, this, (x, y): (i32, i32)| {
-            let cur_layer = this.buffer_view.lock().get_edit_state_mut().get_current_layer().unwrap();
-            let layer_len = this.buffer_view.lock().get_buffer_mut().layers.len();
+            let cur_layer = this.target.lock().get_current_layer().unwrap();
+            let layer_len = this.target.lock().get_buffer_mut().layers.len();
             if cur_layer >= layer_len {
                 return Err(mlua::Error::SyntaxError {
                     message: format!("Current layer {} out of range (0..<{})", cur_layer, layer_len),
                     incomplete_input: false,
                 });
             }
-            let ch = this.buffer_view.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
+            let ch = this.target.lock().get_buffer_mut().layers[cur_layer].get_char((x, y));
             Ok(ch.attribute.get_background())
         });
 
         methods.add_method_mut("print", |_, this, str: String| {
             for c in str.chars() {
-                let mut pos = this.buffer_view.lock().get_caret_mut().get_position();
-                let mut attribute = this.buffer_view.lock().get_caret_mut().get_attribute();
+                let mut pos = this.target.lock().get_caret_mut().get_position();
+                let mut attribute = this.target.lock().get_caret_mut().get_attribute();
                 attribute.attr &= !attribute::INVISIBLE;
                 let ch = AttributedChar::new(this.convert_from_unicode(c.to_string())?, attribute);
-                let _ = this.buffer_view.lock().get_edit_state_mut().set_char(pos, ch);
+                let _ = this.target.lock().set_char(pos, ch);
                 pos.x += 1;
-                this.buffer_view.lock().get_caret_mut().set_position(pos);
+                this.target.lock().get_caret_mut().set_position(pos);
             }
             Ok(())
         });
 
         methods.add_method_mut("gotoxy", |_, this, (x, y): (i32, i32)| {
-            this.buffer_view.lock().get_caret_mut().set_position(Position::new(x, y));
+            this.target.lock().get_caret_mut().set_position(Position::new(x, y));
             Ok(())
         });
 
         methods.add_method_mut("set_layer_position", |_, this, (layer, x, y): (usize, i32, i32)| {
-            if layer < this.buffer_view.lock().get_buffer_mut().layers.len() {
-                let _ = this.buffer_view.lock().get_edit_state_mut().move_layer(Position::new(x, y));
-                Ok(())
-            } else {
-                Err(mlua::Error::SyntaxError {
-                    message: format!("Layer {} out of range (0..<{})", layer, this.buffer_view.lock().get_buffer_mut().layers.len()),
-                    incomplete_input: false,
-                })
-            }
+            this.with_layer(layer, |state| state.move_layer(Position::new(x, y)))?.map_err(engine_error)
         });
         methods.add_method_mut("get_layer_position", |_, this, layer: usize| {
-            if layer < this.buffer_view.lock().get_buffer_mut().layers.len() {
-                let pos = this.buffer_view.lock().get_buffer_mut().layers[layer].get_offset();
+            if layer < this.target.lock().get_buffer_mut().layers.len() {
+                let pos = this.target.lock().get_buffer_mut().layers[layer].get_offset();
                 Ok((pos.x, pos.y))
             } else {
                 Err(mlua::Error::SyntaxError {
-                    message: format!("Layer {} out of range (0..<{})", layer, this.buffer_view.lock().get_buffer_mut().layers.len()),
+                    message: format!("Layer {} out of range (0..<{})", layer, this.target.lock().get_buffer_mut().layers.len()),
                     incomplete_input: false,
                 })
             }
         });
 
-        methods.add_method_mut("set_layer_visible", |_, this, (layer, is_visible): (i32, bool)| {
-            let layer = layer as usize;
-            if layer < this.buffer_view.lock().get_buffer_mut().layers.len() {
-                // todo
-                this.buffer_view.lock().get_buffer_mut().layers[layer].is_visible = is_visible;
-                Ok(())
-            } else {
-                Err(mlua::Error::SyntaxError {
-                    message: format!("Layer {} out of range (0..<{})", layer, this.buffer_view.lock().get_buffer_mut().layers.len()),
-                    incomplete_input: false,
-                })
+        methods.add_method_mut("set_layer_visible", |_, this, (layer, is_visible): (usize, bool)| {
+            this.check_layer(layer)?;
+            let mut state = this.target.lock();
+            if state.get_buffer().layers[layer].is_visible != is_visible {
+                state.toggle_layer_visibility(layer).map_err(engine_error)?;
             }
+            Ok(())
         });
 
         methods.add_method_mut("get_layer_visible", |_, this, layer: usize| {
-            if layer < this.buffer_view.lock().get_buffer_mut().layers.len() {
-                Ok(this.buffer_view.lock().get_buffer_mut().layers[layer].is_visible)
+            if layer < this.target.lock().get_buffer_mut().layers.len() {
+                Ok(this.target.lock().get_buffer_mut().layers[layer].is_visible)
             } else {
                 Err(mlua::Error::SyntaxError {
-                    message: format!("Layer {} out of range (0..<{})", layer, this.buffer_view.lock().get_buffer_mut().layers.len()),
+                    message: format!("Layer {} out of range (0..<{})", layer, this.target.lock().get_buffer_mut().layers.len()),
                     incomplete_input: false,
                 })
             }
         });
 
+        methods.add_method_mut("is_selected", |_, this, (x, y): (i32, i32)| {
+            let offset = this.get_layer_offset();
+            Ok(this.target.lock().get_is_selected(Position::new(x, y) + offset))
+        });
+
+        methods.add_method_mut("is_something_selected", |_, this, ()| Ok(this.target.lock().is_something_selected()));
+
+        methods.add_method_mut("selected_cells", |lua, this, ()| {
+            let mut cells = Vec::new();
+            {
+                let state = this.target.lock();
+                if let Some(layer) = state.get_cur_layer() {
+                    let offset = layer.get_offset();
+                    for y in 0..layer.get_height() {
+                        for x in 0..layer.get_width() {
+                            let pos = Position::new(x, y);
+                            if state.get_is_selected(pos + offset) {
+                                cells.push(pos);
+                            }
+                        }
+                    }
+                }
+            }
+            let mut i = 0;
+            lua.create_function_mut(move |_, ()| {
+                if let Some(pos) = cells.get(i) {
+                    i += 1;
+                    Ok((Some(pos.x), Some(pos.y)))
+                } else {
+                    Ok((None, None))
+                }
+            })
+        });
+
+        methods.add_method_mut("select_rect", |_, this, (x, y, width, height): (i32, i32, i32, i32)| {
+            this.select_rectangle(x, y, width, height, true)
+        });
+
+        methods.add_method_mut("deselect_rect", |_, this, (x, y, width, height): (i32, i32, i32, i32)| {
+            this.select_rectangle(x, y, width, height, false)
+        });
+
+        methods.add_method_mut("clear_selection", |_, this, ()| this.target.lock().clear_selection().map_err(engine_error));
+
+        methods.add_method_mut("add_layer", |_, this, layer: Option<usize>| {
+            let mut state = this.target.lock();
+            let layer_len = state.get_buffer().layers.len();
+            let layer = match layer {
+                Some(layer) => layer,
+                None => state.get_current_layer().unwrap_or(0),
+            };
+            state.add_new_layer(layer).map_err(engine_error)?;
+            Ok(if layer_len == 0 { 0 } else { (layer + 1).min(layer_len) })
+        });
+
+        methods.add_method_mut("remove_layer", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            this.target.lock().remove_layer(layer).map_err(engine_error)
+        });
+
+        methods.add_method_mut("duplicate_layer", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            this.target.lock().duplicate_layer(layer).map_err(engine_error)?;
+            Ok(layer + 1)
+        });
+
+        methods.add_method_mut("raise_layer", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            this.target.lock().raise_layer(layer).map_err(engine_error)
+        });
+
+        methods.add_method_mut("lower_layer", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            this.target.lock().lower_layer(layer).map_err(engine_error)
+        });
+
+        methods.add_method_mut("merge_layer_down", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            this.target.lock().merge_layer_down(layer).map_err(engine_error)
+        });
+
+        methods.add_method_mut("clear_layer", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            this.target.lock().clear_layer(layer).map_err(engine_error)
+        });
+
+        methods.add_method_mut("get_layer_title", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            Ok(this.target.lock().get_buffer().layers[layer].title.clone())
+        });
+
+        methods.add_method_mut("set_layer_title", |_, this, (layer, title): (usize, String)| {
+            this.update_layer_properties(layer, |properties| properties.title = title)
+        });
+
+        methods.add_method_mut("get_layer_size", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            let size = this.target.lock().get_buffer().layers[layer].get_size();
+            Ok((size.width, size.height))
+        });
+
+        methods.add_method_mut("set_layer_size", |_, this, (layer, width, height): (usize, i32, i32)| {
+            this.check_layer(layer)?;
+            this.target.lock().set_layer_size(layer, (width, height)).map_err(engine_error)
+        });
+
+        methods.add_method_mut("get_layer_mode", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            let mode = match this.target.lock().get_buffer().layers[layer].mode {
+                Mode::Normal => "normal",
+                Mode::Chars => "chars",
+                Mode::Attributes => "attributes",
+            };
+            Ok(mode.to_string())
+        });
+
+        methods.add_method_mut("set_layer_mode", |_, this, (layer, mode): (usize, String)| {
+            this.check_layer(layer)?;
+            let mode = match mode.as_str() {
+                "normal" => Mode::Normal,
+                "chars" => Mode::Chars,
+                "attributes" => Mode::Attributes,
+                _ => {
+                    return Err(mlua::Error::SyntaxError {
+                        message: format!("Unknown layer mode '{mode}' (normal, chars, attributes)"),
+                        incomplete_input: false,
+                    })
+                }
+            };
+            this.update_layer_properties(layer, |properties| properties.mode = mode)
+        });
+
+        methods.add_method_mut("get_layer_transparent", |_, this, layer: usize| {
+            this.check_layer(layer)?;
+            Ok(this.target.lock().get_buffer().layers[layer].has_alpha_channel)
+        });
+
+        methods.add_method_mut("set_layer_transparent", |_, this, (layer, has_alpha_channel): (usize, bool)| {
+            this.update_layer_properties(layer, |properties| properties.has_alpha_channel = has_alpha_channel)
+        });
+
+        methods.add_method_mut("make_layer_transparent", |_, this, layer: usize| {
+            this.with_layer(layer, |state| state.make_layer_transparent())?.map_err(engine_error)
+        });
+
+        methods.add_method_mut("get_layer_char", |_, this, (layer, x, y): (usize, i32, i32)| {
+            this.check_layer(layer)?;
+            let ch = this.target.lock().get_buffer().layers[layer].get_char((x, y));
+            Ok(this.convert_to_unicode(ch))
+        });
+
+        methods.add_method_mut("set_layer_char", |_, this, (layer, x, y, ch): (usize, i32, i32, String)| {
+            let mut attr = this.target.lock().get_caret().get_attribute();
+            attr.attr &= !attribute::INVISIBLE;
+            let ch = AttributedChar::new(this.convert_from_unicode(ch)?, attr);
+            this.with_layer(layer, |state| state.set_char((x, y), ch))?.map_err(engine_error)
+        });
+
+        methods.add_method("get_palette_color", |_, this, idx: usize| {
+            this.check_palette_index(idx)?;
+            Ok(this.target.lock().get_buffer().palette.get_rgb(idx as u32))
+        });
+
+        methods.add_method_mut("set_palette_color", |_, this, (idx, r, g, b): (usize, u8, u8, u8)| {
+            this.check_palette_index(idx)?;
+            let mut colors = this.get_palette_colors();
+            colors[idx] = Color::new(r, g, b);
+            this.switch_palette(&colors)
+        });
+
+        methods.add_method("find_palette_color", |_, this, (r, g, b): (u8, u8, u8)| {
+            let colors = this.get_palette_colors();
+            let nearest = colors.iter().enumerate().min_by_key(|(_, c)| {
+                let (cr, cg, cb) = c.get_rgb();
+                let dr = cr as i32 - r as i32;
+                let dg = cg as i32 - g as i32;
+                let db = cb as i32 - b as i32;
+                dr * dr + dg * dg + db * db
+            });
+            Ok(nearest.map(|(i, _)| i))
+        });
+
+        methods.add_method("get_palette", |_, this, ()| {
+            let colors: Vec<Vec<u8>> = this
+                .get_palette_colors()
+                .iter()
+                .map(|c| {
+                    let (r, g, b) = c.get_rgb();
+                    vec![r, g, b]
+                })
+                .collect();
+            Ok(colors)
+        });
+
+        methods.add_method_mut("set_palette", |_, this, colors: Vec<Vec<u8>>| {
+            let mut palette = Vec::new();
+            for (i, color) in colors.iter().enumerate() {
+                let [r, g, b] = color[..] else {
+                    return Err(mlua::Error::SyntaxError {
+                        message: format!("Palette entry {} needs to be {{r, g, b}}", i + 1),
+                        incomplete_input: false,
+                    });
+                };
+                palette.push(Color::new(r, g, b));
+            }
+            if palette.is_empty() {
+                return Err(mlua::Error::SyntaxError {
+                    message: "Palette is empty".to_string(),
+                    incomplete_input: false,
+                });
+            }
+            this.switch_palette(&palette)
+        });
+
+        methods.add_method_mut("swap_palette_colors", |_, this, (a, b): (usize, usize)| {
+            this.check_palette_index(a)?;
+            this.check_palette_index(b)?;
+            let mut colors = this.get_palette_colors();
+            colors.swap(a, b);
+            this.switch_palette(&colors)
+        });
+
+        methods.add_method_mut(
+            "render_tdf",
+            |_, this, (font, text, x, y, outline_style): (mlua::Value, String, Option<i32>, Option<i32>, Option<usize>)| {
+                let fonts = FontTool::get_fonts();
+                let fonts = fonts.lock();
+                let font = match &font {
+                    mlua::Value::Integer(i) => fonts.get(*i as usize),
+                    mlua::Value::String(name) => {
+                        let name = name.to_string_lossy();
+                        fonts.iter().find(|f| f.name.eq_ignore_ascii_case(&name))
+                    }
+                    _ => None,
+                };
+                let Some(font) = font else {
+                    return Err(mlua::Error::SyntaxError {
+                        message: "TDF font not found".to_string(),
+                        incomplete_input: false,
+                    });
+                };
+                let caret = this.target.lock().get_caret().get_position();
+                let pos = Position::new(x.unwrap_or(caret.x), y.unwrap_or(caret.y));
+                let outline_style = outline_style.unwrap_or_else(Settings::get_font_outline_style);
+                this.render_tdf(font, &text, pos, outline_style)
+            },
+        );
+
+        methods.add_method("get_font", |_, this, page: usize| {
+            Ok(this.target.lock().get_buffer().get_font(page).map(|font| font.name.clone()))
+        });
+
+        methods.add_method("get_fonts", |_, this, ()| {
+            let fonts: HashMap<usize, String> = this
+                .target
+                .lock()
+                .get_buffer()
+                .font_iter()
+                .map(|(page, font)| (*page, font.name.clone()))
+                .collect();
+            Ok(fonts)
+        });
+
+        methods.add_method_mut("set_font", |_, this, (page, font): (usize, mlua::Value)| {
+            let font = match &font {
+                mlua::Value::Integer(slot) => BitFont::from_ansi_font_page(*slot as usize).map_err(engine_error)?,
+                mlua::Value::String(name) => BitFont::from_sauce_name(&name.to_string_lossy()).map_err(engine_error)?,
+                _ => {
+                    return Err(mlua::Error::SyntaxError {
+                        message: "Font needs to be an ansi font slot or a sauce font name".to_string(),
+                        incomplete_input: false,
+                    })
+                }
+            };
+            this.with_font_page(page, |state| state.set_font(font)).map_err(engine_error)
+        });
+
         methods.add_method_mut("clear", |_, this, ()| {
-            this.buffer_view.lock().get_buffer_mut().reset_terminal();
+            this.target.lock().get_buffer_mut().reset_terminal();
             Ok(())
         });
     }
 }
+
+/// Converts a buffer char to the unicode char lua scripts work with.
+pub fn convert_to_unicode(buffer_type: icy_engine::BufferType, ch: AttributedChar) -> char {
+    match buffer_type {
+        icy_engine::BufferType::Unicode => ch.ch,
+        icy_engine::BufferType::CP437 => icy_engine::ascii::CP437Converter::default().convert_to_unicode(ch),
+        icy_engine::BufferType::Petscii => icy_engine::petscii::CharConverter::default().convert_to_unicode(ch),
+        icy_engine::BufferType::Atascii => icy_engine::atascii::CharConverter::default().convert_to_unicode(ch),
+        icy_engine::BufferType::Viewdata => icy_engine::viewdata::CharConverter::default().convert_to_unicode(ch),
+    }
+}
+
+fn engine_error(err: impl std::fmt::Display) -> mlua::Error {
+    mlua::Error::SyntaxError {
+        message: format!("{err}"),
+        incomplete_input: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use icy_engine::Buffer;
+
+    use super::*;
+
+    fn create_state() -> Arc<Mutex<EditState>> {
+        let mut buf = Buffer::new((20, 10));
+        buf.is_terminal_buffer = false;
+        Arc::new(Mutex::new(EditState::from_buffer(buf)))
+    }
+
+    /// Runs a script like a plugin as one undo step.
+    fn run_script(state: &Arc<Mutex<EditState>>, script: &str) -> anyhow::Result<()> {
+        let target = EditTarget::Headless(state.clone());
+        run_atomic(&target, "test".to_string(), || {
+            let lua = Lua::new();
+            setup_lua(&lua, target.clone(), &ExecutionControl::new(None))?;
+            lua.load(script).exec()?;
+            Ok(())
+        })
+    }
+
+    /// Title, normal mode, alpha channel and the char at 1, 1 of every layer.
+    fn describe_layers(state: &Arc<Mutex<EditState>>) -> Vec<(String, bool, bool, char)> {
+        state
+            .lock()
+            .get_buffer()
+            .layers
+            .iter()
+            .map(|l| (l.title.clone(), matches!(l.mode, Mode::Normal), l.has_alpha_channel, l.get_char((1, 1)).ch))
+            .collect()
+    }
+
+    #[test]
+    fn test_add_layer() {
+        let state = create_state();
+        run_script(
+            &state,
+            r#"
+            buf:set_layer_char(0, 1, 1, "A")
+            assert(buf:add_layer() == 1)
+            assert(buf.layer_count == 2)
+            assert(buf:get_layer_char(1, 1, 1) ~= "A")
+            buf:set_layer_char(1, 1, 1, "B")
+            assert(buf:add_layer(0) == 1)
+            assert(buf:get_layer_char(2, 1, 1) == "B")
+            "#,
+        )
+        .unwrap();
+        assert_eq!(3, state.lock().get_buffer().layers.len());
+    }
+
+    #[test]
+    fn test_duplicate_layer() {
+        let state = create_state();
+        run_script(
+            &state,
+            r#"
+            buf:add_layer()
+            buf:set_layer_char(1, 1, 1, "B")
+            assert(buf:duplicate_layer(1) == 2)
+            assert(buf.layer_count == 3)
+            assert(buf:get_layer_char(2, 1, 1) == "B")
+            assert(buf:duplicate_layer(0) == 1)
+            assert(buf:get_layer_char(3, 1, 1) == "B")
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_layer_operations_undo() {
+        let state = create_state();
+        run_script(&state, r#"buf:add_layer(0) buf:set_layer_char(1, 1, 1, "X")"#).unwrap();
+        let scripts = [
+            "buf:remove_layer(1)",
+            "buf:merge_layer_down(1)",
+            r#"buf:set_layer_title(1, "changed")"#,
+            r#"buf:set_layer_mode(1, "chars")"#,
+            "buf:set_layer_transparent(1, not buf:get_layer_transparent(1))",
+            r#"buf:set_layer_title(0, "a") buf:set_layer_mode(1, "attributes") buf:remove_layer(0)"#,
+        ];
+        for script in scripts {
+            let before = describe_layers(&state);
+            run_script(&state, script).unwrap();
+            assert_ne!(before, describe_layers(&state), "{script}");
+            state.lock().undo().unwrap();
+            assert_eq!(before, describe_layers(&state), "{script}");
+            state.lock().redo().unwrap();
+            assert_ne!(before, describe_layers(&state), "{script}");
+            state.lock().undo().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_select_rect() {
+        let state = create_state();
+        run_script(
+            &state,
+            r#"
+            buf:add_layer(0)
+            buf.layer = 1
+            buf:set_layer_position(1, 5, 3)
+            buf:select_rect(0, 0, 2, 2)
+            assert(buf:is_something_selected())
+            assert(buf:is_selected(1, 1))
+            assert(not buf:is_selected(2, 1))
+            local count = 0
+            for x, y in buf:selected_cells() do
+                assert(x >= 0 and x < 2 and y >= 0 and y < 2)
+                count = count + 1
+            end
+            assert(count == 4)
+            "#,
+        )
+        .unwrap();
+        let state = state.lock();
+        assert!(state.get_is_selected(Position::new(5, 3)));
+        assert!(state.get_is_selected(Position::new(6, 4)));
+        assert!(!state.get_is_selected(Position::new(1, 1)));
+        assert!(!state.get_is_selected(Position::new(7, 3)));
+    }
+
+    #[test]
+    fn test_deselect_rect() {
+        let state = create_state();
+        run_script(
+            &state,
+            r#"
+            buf:add_layer(0)
+            buf.layer = 1
+            buf:set_layer_position(1, 5, 3)
+            buf:select_rect(0, 0, 2, 2)
+            buf:select_rect(4, 4, 1, 1)
+            buf:deselect_rect(1, 1, 1, 1)
+            assert(not buf:is_selected(1, 1))
+            local cells = {}
+            for x, y in buf:selected_cells() do
+                table.insert(cells, x .. "," .. y)
+            end
+            assert(table.concat(cells, " ") == "0,0 1,0 0,1 4,4", table.concat(cells, " "))
+            buf:clear_selection()
+            assert(not buf:is_something_selected())
+            assert(buf:selected_cells()() == nil)
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_failing_script_rolls_back() {
+        let state = create_state();
+        let before = describe_layers(&state);
+        assert!(run_script(&state, r#"buf:set_layer_title(0, "changed") buf:add_layer() error("stop")"#).is_err());
+        assert_eq!(before, describe_layers(&state));
+    }
+
+    fn create_plugin(text: &str) -> Plugin {
+        Plugin {
+            title: "Test".to_string(),
+            description: None,
+            author: None,
+            category: Vec::new(),
+            shortcut: None,
+            path: PathBuf::from("test.lua"),
+            text: text.to_string(),
+            parameters: Vec::new(),
+            events: PluginEvent::parse(text).unwrap(),
+            key: "test.lua".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_events() {
+        assert_eq!(vec![PluginEvent::Open], PluginEvent::parse("function on_open(path) end").unwrap());
+        assert_eq!(vec![PluginEvent::Save], PluginEvent::parse("on_save = function(path) end").unwrap());
+        assert_eq!(vec![PluginEvent::Save], PluginEvent::parse("function _G.on_save(path) end").unwrap());
+        assert_eq!(vec![PluginEvent::ToolUse], PluginEvent::parse("_G.on_tool_use = handler").unwrap());
+        assert_eq!(vec![PluginEvent::ToolUse], PluginEvent::parse("_G[\"on_tool_use\"] = handler").unwrap());
+        assert_eq!(
+            vec![PluginEvent::BeforeSave, PluginEvent::SelectionChange],
+            PluginEvent::parse("local function f() end\non_before_save = f\non_selection_change = f").unwrap()
+        );
+        assert!(PluginEvent::parse("if on_save == nil then print(on_open) end").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fire_event_runs_script_once() {
+        let state = create_state();
+        let target = EditTarget::Headless(state.clone());
+        let plugin = create_plugin(
+            r#"
+            runs = (runs or 0) + 1
+            calls = 0
+            function on_save(path)
+                calls = calls + 1
+                buf:set_char(0, 0, tostring(runs))
+                buf:set_char(1, 0, tostring(calls))
+            end
+            "#,
+        );
+        for _ in 0..3 {
+            let result = plugin.fire_event(target.clone(), PluginEvent::Save, Some("test.ans".to_string()));
+            assert_eq!(None, result.unwrap());
+        }
+        assert_eq!('1', state.lock().get_buffer().get_char((0, 0)).ch);
+        assert_eq!('3', state.lock().get_buffer().get_char((1, 0)).ch);
+
+        PluginEvent::clear_states(&target);
+        plugin.fire_event(target.clone(), PluginEvent::Save, None).unwrap();
+        assert_eq!('1', state.lock().get_buffer().get_char((1, 0)).ch);
+    }
+
+    #[test]
+    fn test_fire_event_checks_globals() {
+        let state = create_state();
+        let target = EditTarget::Headless(state.clone());
+        // found by the text check, but not a global function after the script ran
+        let plugin = create_plugin("local on_open = 1\nfunction on_save(path) return false, \"no\" end");
+        assert_eq!(vec![PluginEvent::Open, PluginEvent::Save], plugin.events);
+        assert_eq!(None, plugin.fire_event(target.clone(), PluginEvent::Open, None).unwrap());
+        assert_eq!(Some("no".to_string()), plugin.fire_event(target, PluginEvent::Save, None).unwrap());
+    }
+
+    #[test]
+    fn test_parse_shortcut() {
+        let ctrl_shift = Modifiers {
+            ctrl: true,
+            shift: true,
+            ..Modifiers::NONE
+        };
+        assert_eq!(Some((egui::Key::E, ctrl_shift)), parse_shortcut("Ctrl+Shift+E"));
+        assert_eq!(Some((egui::Key::E, ctrl_shift)), parse_shortcut("shift + control + e"));
+        assert_eq!(Some((egui::Key::F5, Modifiers::NONE)), parse_shortcut("f5"));
+        assert_eq!(Some((egui::Key::ArrowUp, Modifiers::ALT)), parse_shortcut("Alt+Up"));
+        assert_eq!(Some((egui::Key::Num1, Modifiers::CTRL)), parse_shortcut("Ctrl+1"));
+    }
+
+    #[test]
+    fn test_parse_invalid_shortcut() {
+        assert_eq!(None, parse_shortcut(""));
+        assert_eq!(None, parse_shortcut("Ctrl"));
+        assert_eq!(None, parse_shortcut("Ctrl+"));
+        assert_eq!(None, parse_shortcut("Ctrl+Foo"));
+        assert_eq!(None, parse_shortcut("Hyper+E"));
+        assert_eq!(None, parse_shortcut("F21"));
+        assert_eq!(None, parse_shortcut("Ctrl+E+F"));
+    }
+}
@@ -0,0 +1,186 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PluginValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterType {
+    Int(Option<i64>, Option<i64>),
+    Float(Option<f64>, Option<f64>),
+    Bool,
+    Choice(Vec<String>),
+    Color,
+    Char,
+    String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginParameter {
+    pub name: String,
+    pub label: String,
+    pub parameter_type: ParameterType,
+    pub default: PluginValue,
+}
+
+impl PluginParameter {
+    /// Parses the parameter declarations of a plugin header:
+    ///
+    /// -- Param: steps: int(1, 80) = 10 "Number of steps"
+    /// -- Param: direction: choice(left, right) = right "Direction"
+    /// -- Param: fill: char = "#" "Fill char"
+    ///
+    /// Types are int, float, bool, choice, color (palette index), char and string.
+    pub fn parse_header(text: &str) -> anyhow::Result<Vec<PluginParameter>> {
+        let declaration = Regex::new(r"^--\s*Param:")?;
+        let re = Regex::new(r#"^--\s*Param:\s*(\w+)\s*:\s*(\w+)\s*(?:\(([^)]*)\))?\s*(?:=\s*("[^"]*"|[^\s"]+))?\s*(?:"([^"]*)")?\s*$"#)?;
+        let mut result = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if !declaration.is_match(line) {
+                continue;
+            }
+            let Some(cap) = re.captures(line) else {
+                return Err(anyhow::anyhow!("Invalid parameter declaration '{line}'"));
+            };
+            let name = cap.get(1).unwrap().as_str().to_string();
+            let type_name = cap.get(2).unwrap().as_str().to_lowercase();
+            let args = cap.get(3).map(|m| m.as_str().split(',').map(|a| a.trim().to_string()).collect::<Vec<String>>());
+            let label = cap.get(5).map(|m| m.as_str().to_string()).unwrap_or_else(|| name.clone());
+
+            let get_arg = |i: usize| args.as_ref().and_then(|a| a.get(i)).filter(|a| !a.is_empty());
+            let parameter_type = match type_name.as_str() {
+                "int" => ParameterType::Int(get_arg(0).map(|a| a.parse()).transpose()?, get_arg(1).map(|a| a.parse()).transpose()?),
+                "float" => ParameterType::Float(get_arg(0).map(|a| a.parse()).transpose()?, get_arg(1).map(|a| a.parse()).transpose()?),
+                "bool" => ParameterType::Bool,
+                "choice" => {
+                    let choices: Vec<String> = args.clone().unwrap_or_default().into_iter().filter(|a| !a.is_empty()).collect();
+                    if choices.is_empty() {
+                        return Err(anyhow::anyhow!("Parameter '{name}': choice needs at least one option"));
+                    }
+                    ParameterType::Choice(choices)
+                }
+                "color" | "colour" => ParameterType::Color,
+                "char" => ParameterType::Char,
+                "string" => ParameterType::String,
+                _ => return Err(anyhow::anyhow!("Parameter '{name}': unknown type '{type_name}'")),
+            };
+
+            let mut parameter = PluginParameter {
+                name,
+                label,
+                default: parameter_type.default_value(),
+                parameter_type,
+            };
+            if let Some(default) = cap.get(4) {
+                parameter.default = parameter.parse_value(default.as_str())?;
+            }
+            result.push(parameter);
+        }
+        Ok(result)
+    }
+
+    pub fn parse_value(&self, value: &str) -> anyhow::Result<PluginValue> {
+        let value = value.trim();
+        let unquoted = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+        let result = match &self.parameter_type {
+            ParameterType::Int(_, _) | ParameterType::Color => PluginValue::Int(unquoted.parse()?),
+            ParameterType::Float(_, _) => PluginValue::Float(unquoted.parse()?),
+            ParameterType::Bool => PluginValue::Bool(unquoted.parse()?),
+            ParameterType::Choice(choices) => {
+                if !choices.iter().any(|c| c == unquoted) {
+                    return Err(anyhow::anyhow!("Parameter '{}': '{unquoted}' is not one of {}", self.name, choices.join(", ")));
+                }
+                PluginValue::String(unquoted.to_string())
+            }
+            ParameterType::Char => {
+                let Some(ch) = unquoted.chars().next() else {
+                    return Err(anyhow::anyhow!("Parameter '{}': empty char", self.name));
+                };
+                PluginValue::String(ch.to_string())
+            }
+            ParameterType::String => PluginValue::String(unquoted.to_string()),
+        };
+        Ok(result)
+    }
+
+    /// Checks if a stored value still fits the declaration - the plugin may have changed since it was stored.
+    pub fn is_valid(&self, value: &PluginValue) -> bool {
+        match (&self.parameter_type, value) {
+            (ParameterType::Int(min, max), PluginValue::Int(i)) => min.map(|min| *i >= min).unwrap_or(true) && max.map(|max| *i <= max).unwrap_or(true),
+            (ParameterType::Float(min, max), PluginValue::Float(f)) => min.map(|min| *f >= min).unwrap_or(true) && max.map(|max| *f <= max).unwrap_or(true),
+            (ParameterType::Bool, PluginValue::Bool(_)) | (ParameterType::Color, PluginValue::Int(_)) | (ParameterType::String, PluginValue::String(_)) => true,
+            (ParameterType::Choice(choices), PluginValue::String(s)) => choices.contains(s),
+            (ParameterType::Char, PluginValue::String(s)) => s.chars().count() == 1,
+            _ => false,
+        }
+    }
+}
+
+impl ParameterType {
+    fn default_value(&self) -> PluginValue {
+        match self {
+            ParameterType::Int(min, _) => PluginValue::Int(min.unwrap_or(0)),
+            ParameterType::Float(min, _) => PluginValue::Float(min.unwrap_or(0.0)),
+            ParameterType::Bool => PluginValue::Bool(false),
+            ParameterType::Choice(choices) => PluginValue::String(choices[0].clone()),
+            ParameterType::Color => PluginValue::Int(7),
+            ParameterType::Char => PluginValue::String(" ".to_string()),
+            ParameterType::String => PluginValue::String(String::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        let header = r##"-- Title: Test
+-- Param: steps: int(1, 80) = 10 "Number of steps"
+-- Param: direction: choice(left, right) = right
+-- Param: fill: char = "#" "Fill char"
+print("-- Param: not a declaration")"##;
+        let parameters = PluginParameter::parse_header(header).unwrap();
+        assert_eq!(3, parameters.len());
+
+        assert_eq!("steps", parameters[0].name);
+        assert_eq!("Number of steps", parameters[0].label);
+        assert_eq!(ParameterType::Int(Some(1), Some(80)), parameters[0].parameter_type);
+        assert_eq!(PluginValue::Int(10), parameters[0].default);
+
+        assert_eq!("direction", parameters[1].label);
+        assert_eq!(
+            ParameterType::Choice(vec!["left".to_string(), "right".to_string()]),
+            parameters[1].parameter_type
+        );
+        assert_eq!(PluginValue::String("right".to_string()), parameters[1].default);
+
+        assert_eq!(ParameterType::Char, parameters[2].parameter_type);
+        assert_eq!(PluginValue::String("#".to_string()), parameters[2].default);
+    }
+
+    #[test]
+    fn test_parse_header_defaults() {
+        let parameters = PluginParameter::parse_header("-- Param: size: float(0.5)\n-- Param: color: color").unwrap();
+        assert_eq!(ParameterType::Float(Some(0.5), None), parameters[0].parameter_type);
+        assert_eq!(PluginValue::Float(0.5), parameters[0].default);
+        assert_eq!(PluginValue::Int(7), parameters[1].default);
+    }
+
+    #[test]
+    fn test_parse_malformed_header() {
+        assert!(PluginParameter::parse_header("-- Param: steps").is_err());
+        assert!(PluginParameter::parse_header("-- Param: steps: int(1, 80) = 10 \"Unterminated").is_err());
+        assert!(PluginParameter::parse_header("-- Param: steps: integer = 10").is_err());
+        assert!(PluginParameter::parse_header("-- Param: steps: int(a, 80)").is_err());
+        assert!(PluginParameter::parse_header("-- Param: steps: int(1, 80) = many").is_err());
+        assert!(PluginParameter::parse_header("-- Param: direction: choice()").is_err());
+        assert!(PluginParameter::parse_header("-- Param: direction: choice(left, right) = up").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use i18n_embed_fl::fl;
+use icy_engine::{
+    editor::{EditState, UndoOperation},
+    EngineResult, Layer, Mode,
+};
+
+/// The layer properties plugins can change that have no undo operation in the engine.
+#[derive(Clone)]
+pub struct LayerProperties {
+    pub title: String,
+    pub mode: Mode,
+    pub has_alpha_channel: bool,
+}
+
+impl LayerProperties {
+    pub fn from_layer(layer: &Layer) -> Self {
+        Self {
+            title: layer.title.clone(),
+            mode: layer.mode,
+            has_alpha_channel: layer.has_alpha_channel,
+        }
+    }
+
+    fn apply(&self, edit_state: &mut EditState, layer: usize) -> EngineResult<()> {
+        let Some(layer) = edit_state.get_buffer_mut().layers.get_mut(layer) else {
+            return Err(anyhow::anyhow!("Layer {layer} not found"));
+        };
+        layer.title = self.title.clone();
+        layer.mode = self.mode;
+        layer.has_alpha_channel = self.has_alpha_channel;
+        edit_state.set_is_buffer_dirty();
+        Ok(())
+    }
+}
+
+pub struct UpdateLayerProperties {
+    layer: usize,
+    old_properties: LayerProperties,
+    new_properties: LayerProperties,
+}
+
+impl UpdateLayerProperties {
+    pub fn new(layer: usize, old_properties: LayerProperties, new_properties: LayerProperties) -> Self {
+        Self {
+            layer,
+            old_properties,
+            new_properties,
+        }
+    }
+}
+
+impl UndoOperation for UpdateLayerProperties {
+    fn get_description(&self) -> String {
+        fl!(crate::LANGUAGE_LOADER, "undo-update-layer-properties")
+    }
+
+    fn undo(&mut self, edit_state: &mut EditState) -> EngineResult<()> {
+        self.old_properties.apply(edit_state, self.layer)
+    }
+
+    fn redo(&mut self, edit_state: &mut EditState) -> EngineResult<()> {
+        self.new_properties.apply(edit_state, self.layer)
+    }
+}
@@ -5,7 +5,7 @@ use egui_bind::{BindTarget, KeyOrPointer};
 use i18n_embed_fl::fl;
 use icy_engine::PaletteMode;
 
-use crate::{button_with_shortcut, DocumentTab, Message, MRU_FILES, SETTINGS};
+use crate::{button_with_shortcut, DocumentTab, Message, MRU_FILES, PLUGINS, SETTINGS};
 
 pub trait CommandState {
     fn is_enabled(&self, _open_tab_opt: Option<&DocumentTab>) -> bool {
@@ -207,6 +207,7 @@ pub struct CommandWrapper {
     label: String,
     pub is_enabled: bool,
     pub is_checked: Option<bool>,
+    pub tooltip: Option<String>,
     state_key: u32,
 }
 
@@ -268,6 +269,8 @@ macro_rules! keys {
 
         pub struct Commands {
             state_map: HashMap<u32, Box<dyn CommandState>>,
+            /// Commands running the loaded plugins, bound by "plugin:<key>" names.
+            pub plugin_commands: Vec<(String, CommandWrapper)>,
             $(
                 pub $l: CommandWrapper,
             )*
@@ -282,6 +285,7 @@ macro_rules! keys {
 
                 Self {
                     state_map,
+                    plugin_commands: Vec::new(),
                     $(
                         $l: CommandWrapper::new(key!($($key, $modifier)?), Message::$message, fl!(crate::LANGUAGE_LOADER, $translation), hash(stringify!($cmd_state))),
                     )*
@@ -307,6 +311,12 @@ macro_rules! keys {
                         return;
                     }
                 )*
+                for (_, cmd) in &self.plugin_commands {
+                    if cmd.is_enabled && cmd.is_pressed(ctx) {
+                        *message = Some(cmd.message.clone());
+                        return;
+                    }
+                }
             }
 
             pub fn update_states(&mut self, open_tab_opt: Option<&DocumentTab>) {
@@ -320,9 +330,13 @@ macro_rules! keys {
                 $(
                     self.$l.update_state(&result_map);
                 )*
+                for (_, cmd) in &mut self.plugin_commands {
+                    cmd.update_state(&result_map);
+                }
             }
 
             pub fn apply_key_bindings(&mut self, key_bindings: &Vec<(String, egui::Key, Modifiers)> ) {
+                self.update_plugin_commands();
                 for (binding, key, modifier) in key_bindings {
                     match binding.as_str() {
                         $(
@@ -331,7 +345,11 @@ macro_rules! keys {
                             }
                         )*
 
-                        _ => {}
+                        _ => {
+                            if let Some((_, cmd)) = self.plugin_commands.iter_mut().find(|(name, _)| name == binding) {
+                                cmd.key = Some((KeyOrPointer::Key(*key), *modifier));
+                            }
+                        }
                     }
                 }
             }
@@ -376,6 +394,25 @@ macro_rules! keys {
                             });
                         }
                     )*
+
+                    for plugin in unsafe { PLUGINS.iter() } {
+                        let name = plugin.get_binding_name();
+                        let label = format!("{}: {}", fl!(crate::LANGUAGE_LOADER, "menu-plugins"), plugin.title);
+                        if filter.is_empty() || label.to_lowercase().contains(filter.to_lowercase().as_str()) {
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                                let mut bind = keys.get(&name).cloned().or(plugin.shortcut);
+                                if ui.add(egui_bind::Bind::new(name.clone(), &mut bind)).changed() {
+                                    if let Some(bind) = bind {
+                                        keys.insert(name.clone(), bind);
+                                    } else {
+                                        keys.remove(&name);
+                                    }
+                                    changed_bindings = true;
+                                }
+                                ui.label(label);
+                            });
+                        }
+                    }
                 });
                 changed_bindings
             }
@@ -393,6 +430,26 @@ fn hash(str: impl Into<String>) -> u32 {
     hasher.finish() as u32
 }
 
+impl Commands {
+    /// Recreates the plugin commands from PLUGINS with the shortcuts from the plugin headers.
+    pub fn update_plugin_commands(&mut self) {
+        let state_key = hash("BufferOpenState");
+        self.plugin_commands = unsafe { PLUGINS.iter() }
+            .enumerate()
+            .map(|(i, plugin)| {
+                let label = if plugin.parameters.is_empty() {
+                    plugin.title.clone()
+                } else {
+                    format!("{}…", plugin.title)
+                };
+                let mut cmd = CommandWrapper::new(plugin.shortcut, Message::RunPlugin(i), label, state_key);
+                cmd.tooltip = plugin.get_tooltip();
+                (plugin.get_binding_name(), cmd)
+            })
+            .collect();
+    }
+}
+
 impl CommandWrapper {
     pub fn new(key: Option<(egui::Key, Modifiers)>, message: Message, description: String, state_key: u32) -> Self {
         let key = key.map(|(k, m)| (KeyOrPointer::Key(k), m));
@@ -403,6 +460,7 @@ impl CommandWrapper {
             state_key,
             is_enabled: true,
             is_checked: None,
+            tooltip: None,
         }
     }
 
@@ -449,7 +507,11 @@ impl CommandWrapper {
             }
         });
 
-        if response.inner.clicked() {
+        let mut response = response.inner;
+        if let Some(tooltip) = &self.tooltip {
+            response = response.on_hover_text(tooltip);
+        }
+        if response.clicked() {
             *message = Some(self.message.clone());
             ui.close_menu();
         }
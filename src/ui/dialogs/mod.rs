@@ -51,3 +51,9 @@ pub use select_palette_dialog::*;
 
 mod settings_dialog;
 pub use settings_dialog::*;
+
+mod plugin_parameter_dialog;
+pub use plugin_parameter_dialog::*;
+
+mod plugin_progress_dialog;
+pub use plugin_progress_dialog::*;
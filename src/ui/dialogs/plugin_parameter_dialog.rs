@@ -0,0 +1,168 @@
+use eframe::{
+    egui::{self, Layout, RichText},
+    epaint::{Color32, Stroke, Vec2},
+};
+use egui_modal::Modal;
+use i18n_embed_fl::fl;
+
+use crate::{
+    plugins::{ParameterType, PluginParameter, PluginValue},
+    AnsiEditor, Message, ModalDialog, TerminalResult, PLUGINS, PLUGIN_PARAMETERS,
+};
+
+pub struct PluginParameterDialog {
+    pub should_commit: bool,
+    /// The plugin key, the index may change when the plugin directory gets reloaded.
+    plugin_key: String,
+    title: String,
+    parameters: Vec<PluginParameter>,
+    values: Vec<PluginValue>,
+    palette: Vec<(u8, u8, u8)>,
+}
+
+impl PluginParameterDialog {
+    pub fn new(plugin: usize, buf: &icy_engine::Buffer) -> Self {
+        let (plugin_key, title, parameters, values) = unsafe {
+            let p = &PLUGINS[plugin];
+            let values = p.get_parameter_values().into_iter().map(|(_, value)| value).collect();
+            (p.get_key(), p.title.clone(), p.parameters.clone(), values)
+        };
+        let palette = buf.palette.color_iter().map(|c| c.get_rgb()).collect();
+        Self {
+            should_commit: false,
+            plugin_key,
+            title,
+            parameters,
+            values,
+            palette,
+        }
+    }
+}
+
+impl ModalDialog for PluginParameterDialog {
+    fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut result = false;
+        let modal = Modal::new(ctx, "plugin_parameter_dialog");
+
+        modal.show(|ui| {
+            modal.title(ui, fl!(crate::LANGUAGE_LOADER, "plugin-parameter-dialog-title", title = self.title.clone()));
+
+            modal.frame(ui, |ui| {
+                egui::Grid::new("plugin_parameter_grid")
+                    .num_columns(2)
+                    .spacing([4.0, 8.0])
+                    .min_row_height(24.)
+                    .show(ui, |ui| {
+                        for (i, (parameter, value)) in self.parameters.iter().zip(self.values.iter_mut()).enumerate() {
+                            ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                                ui.label(&parameter.label);
+                            });
+                            show_parameter(ui, i, parameter, value, &self.palette);
+                            ui.end_row();
+                        }
+                    });
+                ui.add_space(16.0);
+            });
+
+            modal.buttons(ui, |ui| {
+                if ui.button(fl!(crate::LANGUAGE_LOADER, "plugin-parameter-dialog-run-button")).clicked() {
+                    self.should_commit = true;
+                    result = true;
+                }
+                if ui.button(fl!(crate::LANGUAGE_LOADER, "new-file-cancel")).clicked() {
+                    result = true;
+                }
+            });
+        });
+        modal.open();
+        result
+    }
+
+    fn should_commit(&self) -> bool {
+        self.should_commit
+    }
+
+    fn commit(&self, _editor: &mut AnsiEditor) -> TerminalResult<Option<Message>> {
+        unsafe {
+            let values = self
+                .parameters
+                .iter()
+                .zip(self.values.iter())
+                .map(|(p, value)| {
+                    let value = if p.is_valid(value) { value.clone() } else { p.default.clone() };
+                    (p.name.clone(), value)
+                })
+                .collect();
+            PLUGIN_PARAMETERS.set_values(&self.plugin_key, values);
+            let Some(plugin) = PLUGINS.iter().position(|p| p.get_key() == self.plugin_key) else {
+                return Err(anyhow::anyhow!("Plugin {} was removed", self.title));
+            };
+            Ok(Some(Message::ExecutePlugin(plugin)))
+        }
+    }
+}
+
+pub fn show_parameter(ui: &mut egui::Ui, i: usize, parameter: &PluginParameter, value: &mut PluginValue, palette: &[(u8, u8, u8)]) {
+    match (&parameter.parameter_type, value) {
+        (ParameterType::Int(min, max), PluginValue::Int(v)) => {
+            if let (Some(min), Some(max)) = (min, max) {
+                ui.add(egui::Slider::new(v, *min..=*max));
+            } else {
+                ui.add(egui::DragValue::new(v).clamp_range(min.unwrap_or(i64::MIN)..=max.unwrap_or(i64::MAX)));
+            }
+        }
+        (ParameterType::Float(min, max), PluginValue::Float(v)) => {
+            if let (Some(min), Some(max)) = (min, max) {
+                ui.add(egui::Slider::new(v, *min..=*max));
+            } else {
+                ui.add(
+                    egui::DragValue::new(v)
+                        .speed(0.1)
+                        .clamp_range(min.unwrap_or(f64::MIN)..=max.unwrap_or(f64::MAX)),
+                );
+            }
+        }
+        (ParameterType::Bool, PluginValue::Bool(v)) => {
+            ui.checkbox(v, "");
+        }
+        (ParameterType::Choice(choices), PluginValue::String(v)) => {
+            egui::ComboBox::from_id_source(("plugin_parameter_choice", i))
+                .width(150.)
+                .selected_text(RichText::new(v.as_str()))
+                .show_ui(ui, |ui| {
+                    for choice in choices {
+                        ui.selectable_value(v, choice.clone(), choice);
+                    }
+                });
+        }
+        (ParameterType::Color, PluginValue::Int(v)) => {
+            ui.horizontal_wrapped(|ui| {
+                ui.spacing_mut().item_spacing = Vec2::splat(2.0);
+                for (idx, (r, g, b)) in palette.iter().enumerate() {
+                    let selected = *v == idx as i64;
+                    let stroke = if selected {
+                        Stroke::new(2.0, ui.style().visuals.strong_text_color())
+                    } else {
+                        Stroke::new(1.0, Color32::DARK_GRAY)
+                    };
+                    let button = egui::Button::new("")
+                        .fill(Color32::from_rgb(*r, *g, *b))
+                        .stroke(stroke)
+                        .min_size(Vec2::splat(16.0));
+                    if ui.add(button).on_hover_text(idx.to_string()).clicked() {
+                        *v = idx as i64;
+                    }
+                }
+            });
+        }
+        (ParameterType::Char, PluginValue::String(v)) => {
+            ui.add(egui::TextEdit::singleline(v).char_limit(1).desired_width(30.0));
+        }
+        (ParameterType::String, PluginValue::String(v)) => {
+            ui.add(egui::TextEdit::singleline(v));
+        }
+        _ => {
+            ui.label("");
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::{sync::atomic::Ordering, thread::JoinHandle, time::Instant};
+
+use eframe::egui::{self, ProgressBar};
+use egui_modal::Modal;
+use i18n_embed_fl::fl;
+
+use crate::{plugins::ExecutionControl, AnsiEditor, Message, ModalDialog, TerminalResult};
+
+pub struct PluginProgressDialog {
+    title: String,
+    control: ExecutionControl,
+    thread: Option<JoinHandle<anyhow::Result<()>>>,
+    start_time: Instant,
+    error: Option<String>,
+}
+
+impl PluginProgressDialog {
+    pub fn new(title: String, control: ExecutionControl, thread: JoinHandle<anyhow::Result<()>>) -> Self {
+        Self {
+            title,
+            control,
+            thread: Some(thread),
+            start_time: Instant::now(),
+            error: None,
+        }
+    }
+}
+
+impl ModalDialog for PluginProgressDialog {
+    fn show(&mut self, ctx: &egui::Context) -> bool {
+        if self.thread.as_ref().map(|t| t.is_finished()).unwrap_or(true) {
+            if let Some(thread) = self.thread.take() {
+                match thread.join() {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => self.error = Some(format!("{err}")),
+                    Err(_) => self.error = Some("Plugin thread panicked".to_string()),
+                }
+            }
+            return true;
+        }
+
+        let modal = Modal::new(ctx, "plugin_progress_dialog");
+        modal.show(|ui| {
+            ui.set_width(350.);
+            modal.title(ui, fl!(crate::LANGUAGE_LOADER, "plugin-progress-dialog-title", title = self.title.clone()));
+
+            modal.frame(ui, |ui| {
+                let progress = *self.control.progress.lock();
+                if let Some(progress) = progress {
+                    ui.add(ProgressBar::new(progress).show_percentage());
+                } else {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(fl!(
+                            crate::LANGUAGE_LOADER,
+                            "plugin-progress-dialog-elapsed",
+                            seconds = self.start_time.elapsed().as_secs()
+                        ));
+                    });
+                }
+            });
+
+            modal.buttons(ui, |ui| {
+                let cancelled = self.control.cancel.load(Ordering::Relaxed);
+                if ui
+                    .add_enabled(!cancelled, egui::Button::new(fl!(crate::LANGUAGE_LOADER, "new-file-cancel")))
+                    .clicked()
+                {
+                    self.control.cancel.store(true, Ordering::Relaxed);
+                }
+            });
+        });
+        modal.open();
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        false
+    }
+
+    fn should_commit(&self) -> bool {
+        true
+    }
+
+    fn is_blocking(&self) -> bool {
+        true
+    }
+
+    fn commit(&self, _editor: &mut AnsiEditor) -> TerminalResult<Option<Message>> {
+        if let Some(err) = &self.error {
+            return Ok(Some(Message::ShowError(format!("Error running plugin {}: {err}", self.title))));
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for PluginProgressDialog {
+    fn drop(&mut self) {
+        // closing the dialog aborts the plugin - wait for the rollback of the partial changes before the editor takes input again
+        self.control.cancel.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -3,6 +3,7 @@ use std::{fs, path::PathBuf, sync::Arc, time::Instant};
 use crate::{
     create_image,
     model::Tool,
+    plugins::{EditTarget, PluginEvent},
     util::autosave::{remove_autosave, store_auto_save},
     Document, DocumentOptions, Message, Settings, DEFAULT_CHAR_SET_TABLE, FIRST_TOOL, MRU_FILES,
 };
@@ -38,6 +39,14 @@ impl DocumentTab {
             return None;
         };
         let doc = &mut self.doc.lock();
+
+        let target = doc.get_ansi_editor().map(|editor| EditTarget::View(editor.buffer_view.clone()));
+        let path_str = path.to_string_lossy().to_string();
+        if let Some(target) = &target {
+            if let Err(err) = PluginEvent::BeforeSave.fire(target, Some(path_str.clone())) {
+                return Some(Message::ShowError(err.to_string()));
+            }
+        }
         unsafe { MRU_FILES.add_recent_file(path) };
 
         let mut msg = None;
@@ -72,6 +81,11 @@ impl DocumentTab {
         }
         if msg.is_none() {
             remove_autosave(path);
+            if let Some(target) = &target {
+                if let Err(err) = PluginEvent::Save.fire(target, Some(path_str)) {
+                    msg = Some(Message::ShowError(err.to_string()));
+                }
+            }
         }
         msg
     }
@@ -102,7 +116,11 @@ impl DocumentTab {
             return None;
         }
         self.destroyed = true;
-        self.doc.lock().destroy(gl)
+        let doc = self.doc.lock();
+        if let Some(editor) = doc.get_ansi_editor() {
+            PluginEvent::clear_states(&EditTarget::View(editor.buffer_view.clone()));
+        }
+        doc.destroy(gl)
     }
 }
 
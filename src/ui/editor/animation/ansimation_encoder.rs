@@ -0,0 +1,174 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    ops::Range,
+    path::Path,
+    sync::{mpsc::Sender, Arc},
+};
+
+use eframe::egui;
+use i18n_embed_fl::fl;
+use icy_engine::{Buffer, ColorOptimizer, SaveOptions};
+use icy_engine_egui::animations::Animator;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    encoding::{show_settings_ui, AnimationEncoder, FrameStream},
+    frame_diff::write_frame_diff,
+};
+use crate::{TerminalResult, SETTINGS};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum FramePreparation {
+    None,
+    Home,
+    Clear,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnsimationSettings {
+    pub frame_preparation: FramePreparation,
+    /// Only send the cells that changed since the last frame.
+    pub diff_frames: bool,
+    /// Frame delays are emulated by padding the stream for this baud rate, 0 disables the padding.
+    pub baud_rate: u32,
+    /// Sends each frame as IcyTerm DCS macro that gets invoked afterwards, so it's shown at once without flickering.
+    pub use_macros: bool,
+}
+
+impl AnsimationSettings {
+    pub const fn new() -> Self {
+        Self {
+            frame_preparation: FramePreparation::Home,
+            diff_frames: true,
+            baud_rate: 28800,
+            use_macros: false,
+        }
+    }
+}
+
+impl Default for AnsimationSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const BAUD_RATES: [u32; 10] = [0, 300, 1200, 2400, 9600, 14400, 19200, 28800, 38400, 57600];
+
+/// Writes the animation as one ansi stream a BBS or terminal plays back.
+pub struct AnsimationEncoder {}
+
+impl AnimationEncoder for AnsimationEncoder {
+    fn label(&self) -> String {
+        fl!(crate::LANGUAGE_LOADER, "animation_editor_ansi_label")
+    }
+    fn extension(&self) -> String {
+        "ans".to_string()
+    }
+    fn encode(&self, _path: &Path, _frames: FrameStream, _width: usize, _height: usize, _sender: Sender<usize>) -> TerminalResult<()> {
+        // writes the frames straight from the animator in direct_encoding, there are no rendered frames to encode
+        Err(anyhow::anyhow!("{} can't encode rendered frames", self.label()))
+    }
+
+    fn direct_encoding(&self, path: &Path, animator: Arc<std::sync::Mutex<Animator>>, frames: Range<usize>) -> TerminalResult<bool> {
+        let settings = unsafe { SETTINGS.ansimation.clone() };
+        let animator = animator.lock().unwrap();
+        let mut f = BufWriter::new(File::create(path)?);
+
+        let opt = SaveOptions::new();
+        let mut last_frame: Option<Buffer> = None;
+        for (buffer, _, delay) in &animator.frames[frames] {
+            let optimizer = ColorOptimizer::new(buffer, &opt);
+            let frame = optimizer.optimize(buffer);
+
+            let mut data = Vec::new();
+            match settings.frame_preparation {
+                FramePreparation::None => {}
+                FramePreparation::Home => data.extend_from_slice(b"\x1b[H"),
+                FramePreparation::Clear => data.extend_from_slice(b"\x1b[0m\x1b[2J\x1b[H"),
+            }
+            // a cleared screen needs the whole frame again
+            let previous = if settings.diff_frames && settings.frame_preparation != FramePreparation::Clear {
+                last_frame.as_ref()
+            } else {
+                None
+            };
+            write_frame_diff(&mut data, &frame, previous, false);
+
+            if settings.use_macros {
+                data = wrap_in_macro(&data);
+            }
+            let padding = get_padding(data.len(), *delay, settings.baud_rate);
+            f.write_all(&data)?;
+            f.write_all(&vec![0; padding])?;
+            last_frame = Some(frame);
+        }
+        f.flush()?;
+        Ok(true)
+    }
+
+    fn show_ui(&self, ui: &mut egui::Ui) {
+        show_settings_ui(ui, unsafe { &mut SETTINGS.ansimation }, |ui, settings| {
+            ui.horizontal(|ui| {
+                ui.label(fl!(crate::LANGUAGE_LOADER, "animation_editor_ansimation_frame_start"));
+                ui.radio_value(
+                    &mut settings.frame_preparation,
+                    FramePreparation::None,
+                    fl!(crate::LANGUAGE_LOADER, "animation_editor_ansimation_frame_start_none"),
+                );
+                ui.radio_value(
+                    &mut settings.frame_preparation,
+                    FramePreparation::Home,
+                    fl!(crate::LANGUAGE_LOADER, "animation_editor_ansimation_frame_start_home"),
+                );
+                ui.radio_value(
+                    &mut settings.frame_preparation,
+                    FramePreparation::Clear,
+                    fl!(crate::LANGUAGE_LOADER, "animation_editor_ansimation_frame_start_clear"),
+                );
+            });
+            ui.add_enabled(
+                settings.frame_preparation != FramePreparation::Clear,
+                egui::Checkbox::new(&mut settings.diff_frames, fl!(crate::LANGUAGE_LOADER, "animation_editor_diff_frames")),
+            );
+            ui.horizontal(|ui| {
+                ui.label(fl!(crate::LANGUAGE_LOADER, "animation_editor_ansimation_baud_rate"));
+                egui::ComboBox::from_id_source("ansimation_baud_rate")
+                    .selected_text(get_baud_rate_text(settings.baud_rate))
+                    .show_ui(ui, |ui| {
+                        for baud_rate in BAUD_RATES {
+                            ui.selectable_value(&mut settings.baud_rate, baud_rate, get_baud_rate_text(baud_rate));
+                        }
+                    });
+            });
+            ui.checkbox(&mut settings.use_macros, fl!(crate::LANGUAGE_LOADER, "animation_editor_ansimation_use_macros"));
+        });
+    }
+}
+
+fn get_baud_rate_text(baud_rate: u32) -> String {
+    if baud_rate == 0 {
+        fl!(crate::LANGUAGE_LOADER, "animation_editor_ansimation_baud_rate_off")
+    } else {
+        baud_rate.to_string()
+    }
+}
+
+/// Defines the frame as macro 0 (hex encoded) and invokes it.
+fn wrap_in_macro(data: &[u8]) -> Vec<u8> {
+    let mut result = b"\x1bP0;0;1!z".to_vec();
+    for b in data {
+        result.extend_from_slice(format!("{b:02X}").as_bytes());
+    }
+    result.extend_from_slice(b"\x1b\\\x1b[0*z");
+    result
+}
+
+/// Number of padding bytes so the frame lasts `delay` ms at the given baud rate, with 10 bits per byte.
+fn get_padding(frame_len: usize, delay: u32, baud_rate: u32) -> usize {
+    if baud_rate == 0 {
+        return 0;
+    }
+    let delay_bytes = delay as u64 * baud_rate as u64 / 10 / 1000;
+    (delay_bytes as usize).saturating_sub(frame_len)
+}
@@ -0,0 +1,37 @@
+use std::{fs::File, io::BufWriter, path::Path, sync::mpsc::Sender};
+
+use crate::TerminalResult;
+
+use super::encoding::{AnimationEncoder, FrameStream};
+
+pub struct ApngEncoder {}
+
+impl AnimationEncoder for ApngEncoder {
+    fn label(&self) -> String {
+        "APNG".to_string()
+    }
+    fn extension(&self) -> String {
+        "png".to_string()
+    }
+
+    fn encode(&self, path: &Path, frames: FrameStream, width: usize, height: usize, sender: Sender<usize>) -> TerminalResult<()> {
+        if frames.frame_count() == 0 {
+            return Err(anyhow::anyhow!("Animation has no frames."));
+        }
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width as u32, height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        // 0 plays = loop forever
+        encoder.set_animated(frames.frame_count() as u32, 0)?;
+        let mut writer = encoder.write_header()?;
+
+        for (frame_idx, frame) in frames.enumerate() {
+            sender.send(frame_idx)?;
+            let (data, duration) = frame?;
+            writer.set_frame_delay(duration.min(u16::MAX as u32) as u16, 1000)?;
+            writer.write_image_data(&data)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
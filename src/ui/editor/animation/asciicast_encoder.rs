@@ -1,14 +1,39 @@
-use icy_engine::{ColorOptimizer, SaveOptions, StringGenerator, TextPane};
+use eframe::egui;
+use i18n_embed_fl::fl;
+use icy_engine::{Buffer, ColorOptimizer, SaveOptions, StringGenerator, TextPane};
 use icy_engine_egui::animations::Animator;
+use serde::{Deserialize, Serialize};
 use std::{
     fs::File,
     io::Write,
+    ops::Range,
     path::Path,
     sync::{mpsc::Sender, Arc},
 };
 
-use super::encoding::AnimationEncoder;
-use crate::TerminalResult;
+use super::{
+    encoding::{show_settings_ui, AnimationEncoder, FrameStream},
+    frame_diff::write_frame_diff,
+};
+use crate::{TerminalResult, SETTINGS};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AsciiCastSettings {
+    /// Only send the cells that changed since the last frame instead of the whole screen.
+    pub diff_frames: bool,
+}
+
+impl AsciiCastSettings {
+    pub const fn new() -> Self {
+        Self { diff_frames: true }
+    }
+}
+
+impl Default for AsciiCastSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 pub struct AsciiCast {}
 
@@ -19,11 +44,12 @@ impl AnimationEncoder for AsciiCast {
     fn extension(&self) -> String {
         "cast".to_string()
     }
-    fn encode(&self, _path: &Path, _frames: Vec<(Vec<u8>, u32)>, _width: usize, _height: usize, _sender: Sender<usize>) -> TerminalResult<()> {
-        panic!("unsupported");
+    fn encode(&self, _path: &Path, _frames: FrameStream, _width: usize, _height: usize, _sender: Sender<usize>) -> TerminalResult<()> {
+        // writes the frames straight from the animator in direct_encoding, there are no rendered frames to encode
+        Err(anyhow::anyhow!("{} can't encode rendered frames", self.label()))
     }
 
-    fn direct_encoding(&self, path: &Path, animator: Arc<std::sync::Mutex<Animator>>) -> TerminalResult<bool> {
+    fn direct_encoding(&self, path: &Path, animator: Arc<std::sync::Mutex<Animator>>, frames: Range<usize>) -> TerminalResult<bool> {
         let Some(file_stem) = path.file_stem() else {
             return Err(anyhow::anyhow!("invalid file name"));
         };
@@ -31,9 +57,8 @@ impl AnimationEncoder for AsciiCast {
             return Err(anyhow::anyhow!("invalid file name"));
         };
         let mut f = File::create(path)?;
-        let frame_count = animator.lock().unwrap().frames.len();
         {
-            let buf = &animator.lock().unwrap().frames[0].0;
+            let buf = &animator.lock().unwrap().frames[frames.start].0;
             f.write_all(format!("{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": 0, \"title\": \"{}\", \"env\": {{\"TERM\": \"IcyTerm\", \"SHELL\": \"/bin/icy_play\"}}, \"theme\": {{ \"fg\": \"{}\", \"bg\": \"{}\", \"palette\": \"{}\" }}  }}\n", 
                 buf.get_width(),
                 buf.get_height(),
@@ -48,9 +73,11 @@ impl AnimationEncoder for AsciiCast {
                     s
                 })).as_bytes())?;
         }
+        let diff_frames = unsafe { SETTINGS.asciicast.diff_frames };
         let mut timestamp = 0.0;
+        let mut last_frame: Option<Buffer> = None;
 
-        for frame in 0..frame_count {
+        for frame in frames {
             let mut opt: SaveOptions = SaveOptions::new();
             opt.control_char_handling = icy_engine::ControlCharHandling::FilterOut;
             opt.longer_terminal_output = true;
@@ -59,27 +86,47 @@ impl AnimationEncoder for AsciiCast {
             opt.preserve_line_length = true;
             opt.modern_terminal_output = true;
 
-            let mut gen = StringGenerator::new(opt.clone());
-            {
-                let optimizer = ColorOptimizer::new(&animator.lock().unwrap().frames[frame].0, &opt);
-                let buf = optimizer.optimize(&animator.lock().unwrap().frames[frame].0);
+            let buf = {
+                let animator = animator.lock().unwrap();
+                let optimizer = ColorOptimizer::new(&animator.frames[frame].0, &opt);
+                optimizer.optimize(&animator.frames[frame].0)
+            };
+
+            if diff_frames {
+                let mut data = Vec::new();
+                if last_frame.is_none() {
+                    data.extend_from_slice(b"\x1b[0m\x1b[2J");
+                }
+                write_frame_diff(&mut data, &buf, last_frame.as_ref(), true);
+                if !data.is_empty() {
+                    output_line(&mut f, 0, data.len(), &data, timestamp)?;
+                }
+                last_frame = Some(buf);
+            } else {
+                let mut gen = StringGenerator::new(opt.clone());
                 gen.generate(&buf, &buf);
-            }
-            gen.line_offsets.push(gen.get_data().len());
+                gen.line_offsets.push(gen.get_data().len());
 
-            let data = gen.get_data();
-            let mut cur = 0;
-            for i in &gen.line_offsets {
-                if cur < *i {
-                    output_line(&mut f, cur, *i, data, timestamp)?;
+                let data = gen.get_data();
+                let mut cur = 0;
+                for i in &gen.line_offsets {
+                    if cur < *i {
+                        output_line(&mut f, cur, *i, data, timestamp)?;
+                    }
+                    cur = *i;
                 }
-                cur = *i;
             }
             timestamp += animator.lock().unwrap().frames[frame].2 as f64;
         }
 
         Ok(true)
     }
+
+    fn show_ui(&self, ui: &mut egui::Ui) {
+        show_settings_ui(ui, unsafe { &mut SETTINGS.asciicast }, |ui, settings| {
+            ui.checkbox(&mut settings.diff_frames, fl!(crate::LANGUAGE_LOADER, "animation_editor_diff_frames"));
+        });
+    }
 }
 
 fn output_line(f: &mut File, from: usize, to: usize, data: &[u8], timestamp: f64) -> TerminalResult<()> {
@@ -0,0 +1,191 @@
+use eframe::egui::{self, DragValue, RichText};
+use i18n_embed_fl::fl;
+use icy_engine::TextPane;
+use icy_engine_egui::animations::Animator;
+
+use super::highlighting::get_error_line;
+
+const BREAK_START: &str = "<<icy_draw_break>>\n";
+const BREAK_END: &str = "\n<</icy_draw_break>>";
+
+/// Gets prepended to the script as first line. It wraps next_frame so the script stops with an error
+/// carrying the globals after the break frame. `BREAK_FRAME` gets replaced with the frame number.
+const DEBUG_PRELUDE: &str = r#"
+local __icy_next_frame = next_frame
+local __icy_frame = 0
+local __icy_base = {}
+for k in pairs(_G) do __icy_base[k] = true end
+__icy_base.cur_frame = nil
+local function __icy_value(v)
+    if type(v) == "string" then return (string.format("%q", v):gsub("\n", "n")) end
+    if type(v) == "table" then
+        local items = {}
+        for k, e in pairs(v) do
+            if #items >= 16 then items[#items + 1] = "..." break end
+            items[#items + 1] = tostring(k) .. " = " .. tostring(e)
+        end
+        return "{ " .. table.concat(items, ", ") .. " }"
+    end
+    return tostring(v)
+end
+function next_frame(...)
+    __icy_next_frame(...)
+    __icy_frame = __icy_frame + 1
+    if __icy_frame >= BREAK_FRAME then
+        local lines = {}
+        for k, v in pairs(_G) do
+            if not __icy_base[k] and type(v) ~= "function" then lines[#lines + 1] = tostring(k) .. "\t" .. __icy_value(v) end
+        end
+        table.sort(lines)
+        error("<<icy_draw_break>>\n" .. table.concat(lines, "\n") .. "\n<</icy_draw_break>>", 0)
+    end
+end
+"#;
+
+pub enum DebugAction {
+    None,
+    /// Run the script until the break frame.
+    Run,
+    /// Run the script without stopping.
+    Continue,
+}
+
+/// Debug mode of the animation editor, the script gets stopped after a next_frame call to inspect its state.
+pub struct Debugger {
+    pub enabled: bool,
+    /// Frame number (1 based) the script stops after.
+    pub break_frame: usize,
+    stopped_at: Option<usize>,
+    globals: Vec<(String, String)>,
+    /// The running script got the prelude, it shifts the lines by one.
+    pending_instrumented: bool,
+    instrumented: bool,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            break_frame: 1,
+            stopped_at: None,
+            globals: Vec::new(),
+            pending_instrumented: false,
+            instrumented: false,
+        }
+    }
+}
+
+impl Debugger {
+    /// Returns the script to run, with the break prelude in debug mode.
+    pub fn prepare_script(&mut self, txt: &str, stop: bool) -> String {
+        self.pending_instrumented = self.enabled && stop;
+        if !self.pending_instrumented {
+            return txt.to_string();
+        }
+        let prelude: Vec<&str> = DEBUG_PRELUDE.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        format!("{}\n{txt}", prelude.join(" ").replace("BREAK_FRAME", &self.break_frame.to_string()))
+    }
+
+    /// Needs to be called when a script run finished, takes the break out of the error of the animator.
+    pub fn script_finished(&mut self, animator: &mut Animator) {
+        self.instrumented = self.pending_instrumented;
+        self.stopped_at = None;
+        self.globals.clear();
+        if !self.instrumented {
+            return;
+        }
+        let Some(start) = animator.error.find(BREAK_START) else {
+            return;
+        };
+        let dump = &animator.error[start + BREAK_START.len()..];
+        let dump = dump.find(BREAK_END).map(|end| &dump[..end]).unwrap_or(dump);
+        self.globals = dump
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self.stopped_at = Some(animator.frames.len());
+        animator.error.clear();
+    }
+
+    /// Frame number (1 based) the script got stopped at.
+    pub fn stopped_at(&self) -> Option<usize> {
+        self.stopped_at
+    }
+
+    /// Gets the line (1 based) of the error in the code editor.
+    pub fn get_error_line(&self, error: &str) -> Option<usize> {
+        let line = get_error_line(error)?;
+        if self.instrumented {
+            // errors in the prelude are shown at the first line
+            Some(line.saturating_sub(1).max(1))
+        } else {
+            Some(line)
+        }
+    }
+
+    pub fn show_controls(&mut self, ui: &mut egui::Ui) -> DebugAction {
+        let mut action = DebugAction::None;
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut self.enabled, fl!(crate::LANGUAGE_LOADER, "animation_debug_mode")).changed() {
+                action = if self.enabled { DebugAction::Run } else { DebugAction::Continue };
+            }
+            ui.add_enabled_ui(self.enabled, |ui| {
+                ui.label(fl!(crate::LANGUAGE_LOADER, "animation_debug_break_frame"));
+                ui.add(DragValue::new(&mut self.break_frame).clamp_range(1..=usize::MAX));
+                if ui.button(fl!(crate::LANGUAGE_LOADER, "animation_debug_run")).clicked() {
+                    action = DebugAction::Run;
+                }
+                if ui.button(fl!(crate::LANGUAGE_LOADER, "animation_debug_step")).clicked() {
+                    if let Some(frame) = self.stopped_at {
+                        self.break_frame = frame + 1;
+                    }
+                    action = DebugAction::Run;
+                }
+                if ui.button(fl!(crate::LANGUAGE_LOADER, "animation_debug_continue")).clicked() {
+                    action = DebugAction::Continue;
+                }
+            });
+        });
+        action
+    }
+
+    /// Shows the globals and the layers of the frame the script stopped at.
+    pub fn show_state(&self, ui: &mut egui::Ui, animator: &Animator) {
+        let Some(frame) = self.stopped_at else {
+            return;
+        };
+        ui.label(RichText::new(fl!(crate::LANGUAGE_LOADER, "animation_debug_stopped", frame = frame)).strong());
+        ui.columns(2, |columns| {
+            egui::ScrollArea::vertical().id_source("debug_globals").show(&mut columns[0], |ui| {
+                egui::Grid::new("debug_globals_grid").num_columns(2).striped(true).show(ui, |ui| {
+                    ui.label(RichText::new(fl!(crate::LANGUAGE_LOADER, "animation_debug_globals")).strong());
+                    ui.end_row();
+                    for (name, value) in &self.globals {
+                        ui.label(RichText::new(name).monospace());
+                        ui.label(RichText::new(value).monospace());
+                        ui.end_row();
+                    }
+                });
+            });
+
+            egui::ScrollArea::vertical().id_source("debug_layers").show(&mut columns[1], |ui| {
+                egui::Grid::new("debug_layers_grid").num_columns(4).striped(true).show(ui, |ui| {
+                    ui.label(RichText::new(fl!(crate::LANGUAGE_LOADER, "animation_debug_layers")).strong());
+                    ui.end_row();
+                    let Some((buffer, _, _)) = frame.checked_sub(1).and_then(|f| animator.frames.get(f)) else {
+                        return;
+                    };
+                    for layer in &buffer.layers {
+                        ui.label(&layer.title);
+                        let offset = layer.get_offset();
+                        ui.label(format!("{}, {}", offset.x, offset.y));
+                        ui.label(format!("{}x{}", layer.get_width(), layer.get_height()));
+                        ui.label(if layer.is_visible { "👁" } else { "" });
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+    }
+}
@@ -1,77 +1,355 @@
 use egui::Vec2;
 use icy_engine::{Buffer, TextPane};
-use icy_engine_egui::{animations::Animator, BufferView, TerminalCalc};
+use icy_engine_egui::{animations::Animator, BufferView, MonitorSettings, TerminalCalc, TerminalOptions};
+use image::RgbaImage;
 use std::{
+    ops::Range,
     path::{Path, PathBuf},
     sync::{
+        atomic::{AtomicBool, Ordering},
         mpsc::{Receiver, Sender},
-        Arc,
+        Arc, Mutex,
     },
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use super::{asciicast_encoder::AsciiCast, gif_encoder::GifEncoder /*, mp4_encoder::Mp4Encoder*/};
-use crate::TerminalResult;
+use super::{
+    ansimation_encoder::AnsimationEncoder, apng_encoder::ApngEncoder, asciicast_encoder::AsciiCast, gif_encoder::GifEncoder,
+    /*mp4_encoder::Mp4Encoder,*/ png_sequence_encoder::PngSequenceEncoder, webp_encoder::WebpEncoder,
+};
+use crate::{
+    util::render::{render_to_image, RenderOptions},
+    Settings, TerminalResult,
+};
 
 pub trait AnimationEncoder {
     fn label(&self) -> String;
     fn extension(&self) -> String;
-    fn encode(&self, path: &Path, frames: Vec<(Vec<u8>, u32)>, width: usize, height: usize, sender: Sender<usize>) -> TerminalResult<()>;
+    /// Encodes the frames while they get rendered, sends the index of each frame it starts on.
+    fn encode(&self, path: &Path, frames: FrameStream, width: usize, height: usize, sender: Sender<usize>) -> TerminalResult<()>;
 
-    fn direct_encoding(&self, _path: &Path, _animator: Arc<std::sync::Mutex<Animator>>) -> TerminalResult<bool> {
+    /// Encodes the frames straight from the animator, returns false if the encoder works on rendered frames.
+    fn direct_encoding(&self, _path: &Path, _animator: Arc<std::sync::Mutex<Animator>>, _frames: Range<usize>) -> TerminalResult<bool> {
         Ok(false)
     }
+
+    /// Whether rendered frames get the CRT filters of their monitor settings.
+    fn use_monitor_settings(&self) -> bool {
+        true
+    }
+
+    /// Shows the export options of the encoder.
+    fn show_ui(&self, _ui: &mut egui::Ui) {}
 }
-pub const ENCODERS: &[&dyn AnimationEncoder] = &[&GifEncoder {}, /*&Mp4Encoder {},*/ &AsciiCast {}];
-type EncodingThread = (Receiver<usize>, JoinHandle<TerminalResult<()>>);
 
-pub fn start_encoding_thread(
+/// Shows the export options stored in the settings and saves the settings when they got changed.
+pub fn show_settings_ui<T: Clone + PartialEq>(ui: &mut egui::Ui, settings: &mut T, add_contents: impl FnOnce(&mut egui::Ui, &mut T)) {
+    let old_settings = settings.clone();
+    add_contents(ui, settings);
+    if *settings != old_settings {
+        if let Err(err) = Settings::save() {
+            log::error!("Error saving settings: {err}");
+        }
+    }
+}
+pub const ENCODERS: &[&dyn AnimationEncoder] = &[
+    &GifEncoder {},
+    &ApngEncoder {},
+    &WebpEncoder {},
+    &PngSequenceEncoder {},
+    //&Mp4Encoder {},
+    &AsciiCast {},
+    &AnsimationEncoder {},
+];
+/// Frames get rendered on the UI thread in slices of this time, the encoder works on them in parallel.
+const RENDER_TIME_SLICE: Duration = Duration::from_millis(30);
+
+/// Cancelled jobs that are still stopping, a new export to the same file waits until they cleaned up.
+static CANCELLED_JOBS: Mutex<Vec<(PathBuf, JoinHandle<()>)>> = Mutex::new(Vec::new());
+
+/// The rendered frames an encoder gets while the animation is still rendering.
+pub struct FrameStream {
+    receiver: Receiver<(Vec<u8>, u32)>,
+    frame_count: usize,
+    received: usize,
+    abort: Arc<AtomicBool>,
+}
+
+impl FrameStream {
+    pub fn new(frame_count: usize) -> (Sender<(Vec<u8>, u32)>, Self) {
+        let (tx, receiver) = std::sync::mpsc::channel();
+        (
+            tx,
+            Self {
+                receiver,
+                frame_count,
+                received: 0,
+                abort: Arc::new(AtomicBool::new(false)),
+            },
+        )
+    }
+
+    pub fn from_frames(frames: Vec<(Vec<u8>, u32)>) -> Self {
+        let (tx, stream) = Self::new(frames.len());
+        for frame in frames {
+            // can't fail, the receiver is in the stream
+            let _ = tx.send(frame);
+        }
+        stream
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Gets set when the export got cancelled, encoders that keep working after the last frame need to check it.
+    pub fn abort_flag(&self) -> Arc<AtomicBool> {
+        self.abort.clone()
+    }
+}
+
+impl Iterator for FrameStream {
+    type Item = TerminalResult<(Vec<u8>, u32)>;
+
+    /// Fails if the export got cancelled or the rendering stopped before all frames were sent.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.received > self.frame_count {
+            return None;
+        }
+        if !self.abort.load(Ordering::Relaxed) {
+            if self.received == self.frame_count {
+                return None;
+            }
+            if let Ok(frame) = self.receiver.recv() {
+                self.received += 1;
+                return Some(Ok(frame));
+            }
+        }
+        self.received = self.frame_count + 1;
+        Some(Err(anyhow::anyhow!("Encoding cancelled.")))
+    }
+}
+
+/// An export in progress - the frames get rendered incrementally on the UI thread and encoded on a background thread.
+pub struct EncodingJob {
     encoder: usize,
-    gl: Arc<glow::Context>,
     path: PathBuf,
     animator: Arc<std::sync::Mutex<Animator>>,
-) -> TerminalResult<Option<EncodingThread>> {
-    if !animator.lock().unwrap().success() {
-        return Err(anyhow::anyhow!("Animation is not finished."));
+    frames: Range<usize>,
+    next_frame: usize,
+    buffer_view: Arc<eframe::epaint::mutex::Mutex<BufferView>>,
+    opt: TerminalOptions,
+    use_monitor_settings: bool,
+    frame_sender: Option<Sender<(Vec<u8>, u32)>>,
+    abort: Arc<AtomicBool>,
+    progress: Receiver<usize>,
+    encoded_frames: usize,
+    thread: Option<JoinHandle<TerminalResult<()>>>,
+}
+
+impl EncodingJob {
+    /// Starts encoding the frames in the given range of the animation, returns None if the encoder already wrote the file.
+    pub fn start(
+        encoder: usize,
+        gl: &glow::Context,
+        path: PathBuf,
+        animator: Arc<std::sync::Mutex<Animator>>,
+        frames: Range<usize>,
+    ) -> TerminalResult<Option<Self>> {
+        if !animator.lock().unwrap().success() {
+            return Err(anyhow::anyhow!("Animation is not finished."));
+        }
+        if frames.is_empty() || frames.end > animator.lock().unwrap().frames.len() {
+            return Err(anyhow::anyhow!("Invalid frame range."));
+        }
+        wait_for_cancelled_jobs(&path);
+        // the direct encoders write text and are fast enough to run on the UI thread
+        match ENCODERS[encoder].direct_encoding(&path, animator.clone(), frames.clone()) {
+            Ok(true) => return Ok(None),
+            Ok(false) => {}
+            Err(err) => {
+                remove_output(&path);
+                return Err(err);
+            }
+        }
+
+        let mut buffer = Buffer::new((80, 25));
+        buffer.is_terminal_buffer = false;
+        let mut buffer_view = BufferView::from_buffer(gl, buffer);
+        buffer_view.interactive = false;
+        let buffer_view = Arc::new(eframe::epaint::mutex::Mutex::new(buffer_view));
+        animator.lock().unwrap().set_cur_frame(frames.start);
+        animator.lock().unwrap().display_frame(buffer_view.clone());
+        buffer_view.lock().calc = TerminalCalc::from_buffer(&animator.lock().unwrap().frames[frames.start].0);
+
+        let size = buffer_view.lock().get_buffer().get_size();
+        let dim = buffer_view.lock().get_buffer().get_font_dimensions();
+        let width = (size.width * dim.width) as usize;
+        let height = (size.height * dim.height) as usize;
+
+        let (frame_sender, stream) = FrameStream::new(frames.len());
+        let abort = stream.abort_flag();
+        let (tx, progress) = std::sync::mpsc::channel();
+        let thread_path = path.clone();
+        let thread = thread::Builder::new()
+            .name("Encoding".into())
+            .spawn(move || ENCODERS[encoder].encode(&thread_path, stream, width, height, tx))?;
+
+        Ok(Some(Self {
+            encoder,
+            path,
+            animator,
+            next_frame: frames.start,
+            frames,
+            buffer_view,
+            opt: TerminalOptions {
+                stick_to_bottom: false,
+                scale: Some(Vec2::new(1.0, 1.0)),
+                id: Some(egui::Id::new("gif")),
+                ..Default::default()
+            },
+            use_monitor_settings: ENCODERS[encoder].use_monitor_settings(),
+            frame_sender: Some(frame_sender),
+            abort,
+            progress,
+            encoded_frames: 0,
+            thread: Some(thread),
+        }))
     }
-    if ENCODERS[encoder].direct_encoding(&path, animator.clone())? {
-        return Ok(None);
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
     }
-    let (tx, rx) = std::sync::mpsc::channel();
-    let mut buffer = Buffer::new((80, 25));
-    buffer.is_terminal_buffer = false;
-    let mut buffer_view = BufferView::from_buffer(&gl, buffer);
-    buffer_view.interactive = false;
-    let buffer_view = Arc::new(eframe::epaint::mutex::Mutex::new(buffer_view));
-    animator.lock().unwrap().set_cur_frame(0);
-    animator.lock().unwrap().display_frame(buffer_view.clone());
-    buffer_view.lock().calc = TerminalCalc::from_buffer(&animator.lock().unwrap().frames[0].0);
 
-    let frame_count = animator.lock().unwrap().frames.len();
+    pub fn rendered_frames(&self) -> usize {
+        self.next_frame - self.frames.start
+    }
 
-    let mut data = Vec::new();
+    pub fn encoded_frames(&self) -> usize {
+        self.encoded_frames
+    }
 
-    let mut opt = icy_engine_egui::TerminalOptions {
-        stick_to_bottom: false,
-        scale: Some(Vec2::new(1.0, 1.0)),
-        id: Some(egui::Id::new("gif")),
-        ..Default::default()
-    };
+    /// Renders the next frames and checks the encoder, returns false when the job is done.
+    pub fn update(&mut self, gl: &glow::Context) -> TerminalResult<bool> {
+        if let Some(frame_sender) = &self.frame_sender {
+            let start = Instant::now();
+            while self.next_frame < self.frames.end && start.elapsed() < RENDER_TIME_SLICE {
+                self.animator.lock().unwrap().set_cur_frame(self.next_frame);
+                let monitor_settings = self.animator.lock().unwrap().display_frame(self.buffer_view.clone());
+                self.opt.monitor_settings = if self.use_monitor_settings {
+                    monitor_settings
+                } else {
+                    MonitorSettings::default()
+                };
+                let (_, frame) = self.buffer_view.lock().render_buffer(gl, &self.opt);
+                // the encoder only stops early on errors, they're reported by the thread
+                if frame_sender.send((frame, self.animator.lock().unwrap().get_delay())).is_err() {
+                    break;
+                }
+                self.next_frame += 1;
+            }
+            if self.next_frame >= self.frames.end || self.thread.as_ref().map(|t| t.is_finished()).unwrap_or(true) {
+                self.frame_sender = None;
+                self.buffer_view.lock().destroy(gl);
+            }
+        }
+        if let Some(frame) = self.progress.try_iter().last() {
+            self.encoded_frames = frame + 1;
+        }
 
-    for frame in 0..frame_count {
-        animator.lock().unwrap().set_cur_frame(frame);
-        opt.monitor_settings = animator.lock().unwrap().display_frame(buffer_view.clone());
-        let (_, frame) = buffer_view.lock().render_buffer(&gl, &opt);
-        data.push((frame, animator.lock().unwrap().get_delay()));
+        let Some(thread) = &self.thread else {
+            return Ok(false);
+        };
+        if !thread.is_finished() {
+            return Ok(true);
+        }
+        let result = match self.thread.take().unwrap().join() {
+            Ok(result) => result,
+            Err(err) => {
+                let msg = if let Some(msg) = err.downcast_ref::<&'static str>() {
+                    msg.to_string()
+                } else if let Some(msg) = err.downcast_ref::<String>() {
+                    msg.clone()
+                } else {
+                    format!("?{:?}", err)
+                };
+                Err(anyhow::anyhow!("Thread aborted: {:?}", msg))
+            }
+        };
+        if result.is_err() {
+            remove_output(&self.path);
+        }
+        result.map(|_| false)
     }
 
-    let size = buffer_view.lock().get_buffer().get_size();
-    let dim = buffer_view.lock().get_buffer().get_font_dimensions();
-    let width = (size.width * dim.width) as usize;
-    let height = (size.height * dim.height) as usize;
-    let t = thread::Builder::new()
-        .name("Encoding".into())
-        .spawn(move || ENCODERS[encoder].encode(&path, data, width, height, tx))?;
+    /// Stops rendering and encoding, the partial output gets deleted once the encoder stopped.
+    pub fn cancel(mut self, gl: &glow::Context) {
+        self.abort.store(true, Ordering::Relaxed);
+        if self.frame_sender.take().is_some() {
+            self.buffer_view.lock().destroy(gl);
+        }
+        let path = self.path.clone();
+        let encoder = ENCODERS[self.encoder].label();
+        if let Some(thread) = self.thread.take() {
+            let cleanup_path = path.clone();
+            let cleanup = thread::Builder::new().name("Cancel encoding".into()).spawn(move || {
+                let _ = thread.join();
+                remove_output(&cleanup_path);
+                log::info!("Cancelled {encoder} export of {}", cleanup_path.display());
+            });
+            match cleanup {
+                Ok(cleanup) => CANCELLED_JOBS.lock().unwrap().push((path, cleanup)),
+                Err(err) => log::error!("Error cancelling export of {}: {err}", path.display()),
+            }
+        }
+    }
+}
 
-    Ok(Some((rx, t)))
+/// Blocks until the cancelled jobs writing to path stopped and removed their output.
+fn wait_for_cancelled_jobs(path: &Path) {
+    let jobs = {
+        let mut cancelled_jobs = CANCELLED_JOBS.lock().unwrap();
+        cancelled_jobs.retain(|(_, cleanup)| !cleanup.is_finished());
+        let (jobs, others): (Vec<_>, Vec<_>) = cancelled_jobs.drain(..).partition(|(job_path, _)| job_path == path);
+        *cancelled_jobs = others;
+        jobs
+    };
+    for (_, cleanup) in jobs {
+        let _ = cleanup.join();
+    }
+}
+
+fn remove_output(path: &Path) {
+    if path.exists() {
+        if let Err(err) = std::fs::remove_file(path) {
+            log::error!("Error removing {}: {err}", path.display());
+        }
+    }
+}
+
+/// Renders all frames on the CPU - unlike EncodingJob this doesn't need a gl context.
+pub fn render_frames(animator: &Arc<std::sync::Mutex<Animator>>) -> TerminalResult<(Vec<(Vec<u8>, u32)>, usize, usize)> {
+    let animator = animator.lock().unwrap();
+    if !animator.success() {
+        return Err(anyhow::anyhow!("Animation is not finished."));
+    }
+    let options = RenderOptions::default();
+    let mut size = None;
+    let mut data = Vec::new();
+    for (buffer, _, delay) in &animator.frames {
+        let mut img = render_to_image(buffer, &options)?;
+        let (width, height) = *size.get_or_insert(img.dimensions());
+        // all frames need the size of the first one
+        if img.dimensions() != (width, height) {
+            let mut frame = RgbaImage::new(width, height);
+            image::imageops::overlay(&mut frame, &img, 0, 0);
+            img = frame;
+        }
+        data.push((img.into_raw(), *delay));
+    }
+    let Some((width, height)) = size else {
+        return Err(anyhow::anyhow!("Animation has no frames."));
+    };
+    Ok((data, width as usize, height as usize))
 }
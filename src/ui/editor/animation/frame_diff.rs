@@ -0,0 +1,155 @@
+use icy_engine::{attribute, AttributedChar, Buffer, BufferType, Position, TextAttribute, TextPane, DOS_DEFAULT_PALETTE};
+
+use crate::plugins::convert_to_unicode;
+
+/// ANSI color numbers of the DOS palette order.
+const DOS_TO_ANSI: [u32; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+/// Writes the cells that differ from the previous frame as cursor moves and chars, all cells without previous frame.
+/// With utf8 the chars get converted to unicode for modern terminals, otherwise they're written in the buffer encoding.
+pub fn write_frame_diff(data: &mut Vec<u8>, frame: &Buffer, previous: Option<&Buffer>, utf8: bool) {
+    let dos_palette = uses_dos_palette(frame);
+    let mut cur_attr = None;
+    for y in 0..frame.get_height() {
+        let mut cursor = None;
+        for x in 0..frame.get_width() {
+            let pos = Position::new(x, y);
+            let ch = frame.get_char(pos);
+            if let Some(previous) = previous {
+                if x < previous.get_width() && y < previous.get_height() && previous.get_char(pos) == ch {
+                    continue;
+                }
+            }
+            if cursor != Some(x) {
+                data.extend_from_slice(format!("\x1b[{};{}H", y + 1, x + 1).as_bytes());
+            }
+            if cur_attr != Some(ch.attribute) {
+                data.extend_from_slice(get_sgr(frame, ch.attribute, dos_palette).as_bytes());
+                cur_attr = Some(ch.attribute);
+            }
+            push_char(data, frame.buffer_type, ch, utf8);
+            cursor = Some(x + 1);
+        }
+    }
+}
+
+/// The ANSI colors only match the DOS default palette, other palettes need 24 bit colors.
+fn uses_dos_palette(buf: &Buffer) -> bool {
+    buf.palette.len() >= 16 && buf.palette.color_iter().zip(DOS_DEFAULT_PALETTE.iter()).all(|(a, b)| a == b)
+}
+
+fn get_sgr(buf: &Buffer, attr: TextAttribute, dos_palette: bool) -> String {
+    let mut params = vec!["0".to_string()];
+    let fg = attr.get_foreground();
+    if dos_palette && fg < 16 {
+        if fg >= 8 || attr.is_bold() {
+            params.push("1".to_string());
+        }
+        params.push((30 + DOS_TO_ANSI[fg as usize % 8]).to_string());
+    } else {
+        // bold low colors are shown in their high intensity variant
+        let fg = if attr.is_bold() && fg < 8 && buf.palette.len() >= 16 { fg + 8 } else { fg };
+        let (r, g, b) = buf.palette.get_color(fg).get_rgb();
+        params.push(format!("38;2;{r};{g};{b}"));
+    }
+    let bg = attr.get_background();
+    if dos_palette && bg < 16 {
+        // high intensity backgrounds are shown with blink in ice mode
+        if bg >= 8 || attr.is_blinking() {
+            params.push("5".to_string());
+        }
+        params.push((40 + DOS_TO_ANSI[bg as usize % 8]).to_string());
+    } else {
+        if attr.is_blinking() {
+            params.push("5".to_string());
+        }
+        let (r, g, b) = buf.palette.get_color(bg).get_rgb();
+        params.push(format!("48;2;{r};{g};{b}"));
+    }
+    format!("\x1b[{}m", params.join(";"))
+}
+
+/// Bytes a terminal interprets instead of showing their CP437 glyph: NUL, BEL, BS, TAB, LF, CR, EOF and ESC.
+const CONTROL_BYTES: [u32; 8] = [0x00, 0x07, 0x08, 0x09, 0x0A, 0x0D, 0x1A, 0x1B];
+
+fn push_char(data: &mut Vec<u8>, buffer_type: BufferType, ch: AttributedChar, utf8: bool) {
+    let c = if utf8 { convert_to_unicode(buffer_type, ch) } else { ch.ch };
+    let unicode = utf8 || matches!(buffer_type, BufferType::Unicode);
+    // in unicode all chars below 0x20 are control chars, the CP437 glyphs got already converted
+    let is_control = if unicode { (c as u32) < 0x20 } else { CONTROL_BYTES.contains(&(c as u32)) };
+    if ch.attribute.attr & attribute::INVISIBLE != 0 || is_control {
+        data.push(b' ');
+        return;
+    }
+    if unicode {
+        let mut bytes = [0; 4];
+        data.extend_from_slice(c.encode_utf8(&mut bytes).as_bytes());
+    } else {
+        data.push(c as u32 as u8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use icy_engine::{AttributedChar, Buffer, TextAttribute};
+
+    use super::write_frame_diff;
+    use crate::cli::compare_buffers;
+
+    /// A line of ch and a single control char, both in DOS colors.
+    fn create_frame(ch: char, control: char, fg: u32, bg: u32) -> Buffer {
+        let mut buf = Buffer::new((80, 25));
+        let mut attr = TextAttribute::default();
+        attr.set_foreground(fg);
+        attr.set_background(bg);
+        for x in 0..10 {
+            buf.layers[0].set_char((x, 2), AttributedChar::new(ch, attr));
+        }
+        buf.layers[0].set_char((40, 3), AttributedChar::new(control, attr));
+        buf
+    }
+
+    fn load_ansi(data: &[u8]) -> Buffer {
+        Buffer::from_bytes(Path::new("frame.ans"), true, data).unwrap()
+    }
+
+    #[test]
+    fn test_write_frame() {
+        // red on blue, the palette order differs between DOS and ANSI
+        let frame = create_frame('\x01', '\x1B', 4, 1);
+        let mut data = Vec::new();
+        write_frame_diff(&mut data, &frame, None, false);
+        assert!(data.windows(2).any(|w| w == [b'm', 0x01]));
+        assert!(data.windows(2).filter(|w| w[0] == 0x1B).all(|w| w[1] == b'['));
+
+        // the escape char can't be written, it's replaced by a space
+        let expected = create_frame('\x01', ' ', 4, 1);
+        assert_eq!(None, compare_buffers(&expected, &load_ansi(&data)));
+    }
+
+    #[test]
+    fn test_write_frame_diff() {
+        let first = create_frame('\x01', '\x1B', 4, 1);
+        let second = create_frame('\x03', '\x1B', 6, 2);
+        let mut data = Vec::new();
+        write_frame_diff(&mut data, &first, None, false);
+        let len = data.len();
+        write_frame_diff(&mut data, &second, Some(&first), false);
+        assert!(data.len() - len < len);
+
+        let expected = create_frame('\x03', ' ', 6, 2);
+        assert_eq!(None, compare_buffers(&expected, &load_ansi(&data)));
+    }
+
+    #[test]
+    fn test_write_frame_utf8() {
+        let frame = create_frame('\x01', '\x1B', 4, 1);
+        let mut data = Vec::new();
+        write_frame_diff(&mut data, &frame, None, true);
+        let text = String::from_utf8(data).unwrap();
+        assert!(text.contains("\x1b[0;31;44m☺"));
+        assert!(text.contains('←'));
+    }
+}
@@ -1,12 +1,63 @@
-use std::{path::Path, sync::mpsc::Sender};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+};
 
-use gifski::{progress::NoProgress, Repeat};
+use eframe::egui::{self, Slider};
+use gifski::{progress::ProgressReporter, Repeat};
+use i18n_embed_fl::fl;
+use image::{
+    codecs::gif,
+    imageops::{self, FilterType},
+    Delay, Frame, RgbaImage,
+};
 use imgref::ImgVec;
 use rgb::RGBA8;
+use serde::{Deserialize, Serialize};
 
-use crate::TerminalResult;
+use crate::{TerminalResult, SETTINGS};
 
-use super::encoding::AnimationEncoder;
+use super::encoding::{show_settings_ui, AnimationEncoder, FrameStream};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GifSettings {
+    /// 1-100, only used with dithering
+    pub quality: u8,
+    pub fast: bool,
+    /// 0 loops forever
+    pub loop_count: u16,
+    /// Integer upscaling of the frames, keeps the pixels sharp.
+    pub scale: u32,
+    /// Dithering smoothes gradients, without dithering frames with up to 256 colors are stored exactly.
+    pub dithering: bool,
+    /// Render the frames with the CRT filters of the monitor settings.
+    pub apply_monitor_settings: bool,
+}
+
+impl GifSettings {
+    pub const fn new() -> Self {
+        Self {
+            quality: 100,
+            fast: true,
+            loop_count: 0,
+            scale: 1,
+            dithering: true,
+            apply_monitor_settings: true,
+        }
+    }
+}
+
+impl Default for GifSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 pub struct GifEncoder {}
 
@@ -18,36 +69,146 @@ impl AnimationEncoder for GifEncoder {
         "gif".to_string()
     }
 
-    fn encode(&self, path: &Path, frames: Vec<(Vec<u8>, u32)>, width: usize, height: usize, sender: Sender<usize>) -> TerminalResult<()> {
-        let settings = gifski::Settings {
-            width: Some(width as u32),
-            height: Some(height as u32),
-            quality: 100,
-            fast: true,
-            repeat: Repeat::Infinite,
-        };
+    fn encode(&self, path: &Path, frames: FrameStream, width: usize, height: usize, sender: Sender<usize>) -> TerminalResult<()> {
+        let settings = unsafe { SETTINGS.gif.clone() };
+        let scale = settings.scale.max(1);
+        let aborted = frames.abort_flag();
+        let frames = frames.map(|frame| {
+            let (data, duration) = frame?;
+            let Some(img) = RgbaImage::from_raw(width as u32, height as u32, data) else {
+                return Err(anyhow::anyhow!("Frame has the wrong size"));
+            };
+            let img = if scale > 1 {
+                imageops::resize(&img, width as u32 * scale, height as u32 * scale, FilterType::Nearest)
+            } else {
+                img
+            };
+            Ok((img, duration))
+        });
+        let (width, height) = (width as u32 * scale, height as u32 * scale);
+        if settings.dithering {
+            encode_gifski(path, frames, width, height, &settings, aborted, sender)
+        } else {
+            encode_exact(path, frames, &settings, sender)
+        }
+    }
 
-        let (c, w) = gifski::new(settings)?;
-        let mut time = 0.0;
-        let mut pb = NoProgress {};
-        let path = path.to_path_buf();
-        let fs = std::fs::File::create(path)?;
-        std::thread::spawn(move || w.write(fs, &mut pb).unwrap());
+    fn use_monitor_settings(&self) -> bool {
+        unsafe { SETTINGS.gif.apply_monitor_settings }
+    }
 
-        for (frame_idx, (data, duration)) in frames.into_iter().enumerate() {
+    fn show_ui(&self, ui: &mut egui::Ui) {
+        show_settings_ui(ui, unsafe { &mut SETTINGS.gif }, |ui, settings| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.dithering, fl!(crate::LANGUAGE_LOADER, "animation_editor_gif_dithering"));
+                ui.add_enabled(
+                    settings.dithering,
+                    Slider::new(&mut settings.quality, 1..=100).text(fl!(crate::LANGUAGE_LOADER, "animation_editor_gif_quality")),
+                );
+                ui.checkbox(&mut settings.fast, fl!(crate::LANGUAGE_LOADER, "animation_editor_gif_fast"));
+            });
+            ui.horizontal(|ui| {
+                ui.label(fl!(crate::LANGUAGE_LOADER, "animation_editor_gif_loop_count"));
+                ui.add(egui::DragValue::new(&mut settings.loop_count));
+                if settings.loop_count == 0 {
+                    ui.small(fl!(crate::LANGUAGE_LOADER, "animation_editor_gif_loop_forever"));
+                }
+                ui.separator();
+                ui.label(fl!(crate::LANGUAGE_LOADER, "animation_editor_gif_scale"));
+                for scale in 1..=4 {
+                    ui.selectable_value(&mut settings.scale, scale, format!("{scale}x"));
+                }
+            });
+            ui.checkbox(
+                &mut settings.apply_monitor_settings,
+                fl!(crate::LANGUAGE_LOADER, "animation_editor_gif_monitor_settings"),
+            );
+        });
+    }
+}
+
+fn encode_gifski(
+    path: &Path,
+    frames: impl Iterator<Item = TerminalResult<(RgbaImage, u32)>>,
+    width: u32,
+    height: u32,
+    settings: &GifSettings,
+    aborted: Arc<AtomicBool>,
+    sender: Sender<usize>,
+) -> TerminalResult<()> {
+    let gifski_settings = gifski::Settings {
+        width: Some(width),
+        height: Some(height),
+        quality: settings.quality.clamp(1, 100),
+        fast: settings.fast,
+        repeat: if settings.loop_count == 0 {
+            Repeat::Infinite
+        } else {
+            Repeat::Finite(settings.loop_count)
+        },
+    };
+
+    let (c, w) = gifski::new(gifski_settings)?;
+    let mut pb = AbortProgress(aborted.clone());
+    let fs = File::create(path)?;
+    let writer = std::thread::spawn(move || w.write(fs, &mut pb));
+
+    let add_frames = || -> TerminalResult<()> {
+        let mut time = 0.0;
+        for (frame_idx, frame) in frames.enumerate() {
             sender.send(frame_idx)?;
-            let mut n = 0;
-            let mut d = Vec::new();
-            while n < data.len() {
-                d.push(rgb::RGBA::new(data[n], data[n + 1], data[n + 2], data[n + 3]));
-                n += 4;
-            }
-
-            let img: ImgVec<RGBA8> = imgref::Img::new(d, width, height);
+            let (img, duration) = frame?;
+            let d = img.pixels().map(|p| rgb::RGBA::new(p[0], p[1], p[2], p[3])).collect();
+            let img: ImgVec<RGBA8> = imgref::Img::new(d, width as usize, height as usize);
             c.add_frame_rgba(frame_idx, img, time / 1000.0)?;
             time += duration as f64;
         }
-
         Ok(())
+    };
+    let result = add_frames();
+    if result.is_err() {
+        aborted.store(true, Ordering::Relaxed);
+    }
+    drop(c);
+
+    let write_result = writer.join();
+    result?;
+    match write_result {
+        Ok(result) => result?,
+        Err(_) => return Err(anyhow::anyhow!("GIF writer thread panicked")),
+    }
+    Ok(())
+}
+
+/// Stops the gifski writer when adding the frames failed or the export got cancelled, it keeps writing after the last frame got added.
+struct AbortProgress(Arc<AtomicBool>);
+
+impl ProgressReporter for AbortProgress {
+    fn increase(&mut self) -> bool {
+        !self.0.load(Ordering::Relaxed)
+    }
+
+    fn done(&mut self, _msg: &str) {}
+}
+
+/// Encodes without dithering, the palette of each frame is quantized with NeuQuant only if it has more than 256 colors.
+fn encode_exact(
+    path: &Path,
+    frames: impl Iterator<Item = TerminalResult<(RgbaImage, u32)>>,
+    settings: &GifSettings,
+    sender: Sender<usize>,
+) -> TerminalResult<()> {
+    let speed = if settings.fast { 30 } else { 10 };
+    let mut encoder = gif::GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), speed);
+    encoder.set_repeat(if settings.loop_count == 0 {
+        gif::Repeat::Infinite
+    } else {
+        gif::Repeat::Finite(settings.loop_count)
+    })?;
+    for (frame_idx, frame) in frames.enumerate() {
+        sender.send(frame_idx)?;
+        let (img, duration) = frame?;
+        encoder.encode_frame(Frame::from_parts(img, 0, 0, Delay::from_numer_denom_ms(duration, 1)))?;
     }
+    Ok(())
 }
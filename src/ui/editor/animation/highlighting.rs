@@ -1,4 +1,8 @@
 use super::Syntax;
+use eframe::{
+    egui::{self, text::CCursor, text_edit::TextEditOutput, Rect},
+    epaint::{pos2, Vec2},
+};
 use std::collections::HashSet;
 
 #[must_use]
@@ -17,6 +21,9 @@ pub fn lua() -> Syntax {
             "new_buffer",
             "load_buffer",
             "next_frame",
+            "tween",
+            "keyframes",
+            "ease",
             "fg_rgb",
             "bg_rgb",
             "set_char",
@@ -35,3 +42,31 @@ pub fn lua() -> Syntax {
         ]),
     }
 }
+
+/// Gets the line (1 based) of the first location in a lua error message.
+pub fn get_error_line(error: &str) -> Option<usize> {
+    let parts: Vec<&str> = error.lines().next()?.split(':').collect();
+    // the line is between two colons, like in "[string \"anim\"]:12: message"
+    parts[1..parts.len().saturating_sub(1).max(1)].iter().find_map(|part| part.parse().ok())
+}
+
+/// Highlights a line (1 based) of the code editor and puts a marker in the line number gutter.
+pub fn paint_error_marker(ui: &egui::Ui, output: &TextEditOutput, text: &str, line: usize) {
+    if line == 0 || line > text.split('\n').count() {
+        return;
+    }
+    let line_start: usize = text.split('\n').take(line - 1).map(|l| l.chars().count() + 1).sum();
+    let galley = &output.galley;
+    let row = galley.pos_from_cursor(&galley.from_ccursor(CCursor::new(line_start)));
+
+    let rect = output.response.rect;
+    let top = output.text_draw_pos.y + row.top();
+    let row_rect = Rect::from_x_y_ranges(rect.x_range(), top..=top + row.height());
+    if !output.text_clip_rect.intersects(row_rect) {
+        return;
+    }
+    let color = ui.visuals().error_fg_color;
+    let painter = ui.painter_at(output.text_clip_rect.expand2(Vec2::new(10.0, 0.0)));
+    painter.rect_filled(row_rect, 0.0, color.linear_multiply(0.15));
+    painter.circle_filled(pos2(rect.left() - 6.0, row_rect.center().y), 4.0, color);
+}
@@ -1,6 +1,6 @@
 use std::{
     path::{Path, PathBuf},
-    sync::{mpsc::Receiver, Arc},
+    sync::Arc,
     time::Instant,
 };
 
@@ -15,12 +15,28 @@ use i18n_embed_fl::fl;
 use icy_engine::{ascii, AttributedChar, Buffer, EngineResult, Size, TextAttribute, UnicodeConverter};
 use icy_engine_egui::{animations::Animator, show_terminal_area, BufferView, MonitorSettings};
 
-use self::encoding::{start_encoding_thread, ENCODERS};
+mod ansimation_encoder;
+pub use ansimation_encoder::*;
+mod apng_encoder;
 mod asciicast_encoder;
+pub use asciicast_encoder::*;
+mod debugger;
+use debugger::{DebugAction, Debugger};
 mod encoding;
+pub use encoding::*;
+mod frame_diff;
 mod gif_encoder;
-mod highlighting;
+pub use gif_encoder::*;
+pub mod highlighting;
 //mod mp4_encoder;
+mod onion_skin;
+use onion_skin::OnionSkin;
+mod png_sequence_encoder;
+mod timeline;
+mod tween;
+pub use tween::*;
+use timeline::{Timeline, TIMELINE_HEIGHT};
+mod webp_encoder;
 
 pub struct AnimationEditor {
     gl: Arc<glow::Context>,
@@ -37,6 +53,9 @@ pub struct AnimationEditor {
     parent_path: Option<PathBuf>,
     export_path: PathBuf,
     export_type: usize,
+    timeline: Timeline,
+    onion_skin: OnionSkin,
+    debugger: Debugger,
 
     first_frame: bool,
 
@@ -45,10 +64,7 @@ pub struct AnimationEditor {
     cursor_index: usize,
     scale: f32,
 
-    rx: Option<Receiver<usize>>,
-    thread: Option<std::thread::JoinHandle<TerminalResult<()>>>,
-    cur_encoding_frame: usize,
-    encoding_frames: usize,
+    encoding_job: Option<EncodingJob>,
     encoding_error: String,
 }
 
@@ -60,7 +76,7 @@ impl AnimationEditor {
         buffer_view.interactive = false;
         let buffer_view = Arc::new(eframe::epaint::mutex::Mutex::new(buffer_view));
         let parent_path = path.parent().map(|p| p.to_path_buf());
-        let animator = Animator::run(&parent_path, txt.clone());
+        let animator = Animator::run(&parent_path, add_animation_library(&txt));
         let export_path = path.with_extension("gif");
         Self {
             gl: gl.clone(),
@@ -71,6 +87,9 @@ impl AnimationEditor {
             undostack: 0,
             export_path,
             export_type: 0,
+            timeline: Timeline::default(),
+            onion_skin: OnionSkin::default(),
+            debugger: Debugger::default(),
             parent_path,
             set_frame: 0,
             scale: 1.0,
@@ -78,21 +97,30 @@ impl AnimationEditor {
             shedule_update: false,
             last_update: Instant::now(),
             first_frame: true,
-            rx: None,
-            thread: None,
-            cur_encoding_frame: 0,
-            encoding_frames: 0,
+            encoding_job: None,
             cursor_index: 0,
             encoding_error: String::new(),
         }
     }
 
+    /// Runs the script again, in debug mode it stops at the break frame if `stop` is set.
+    fn run_script(&mut self, stop: bool) {
+        let txt = self.debugger.prepare_script(&add_animation_library(&self.txt), stop);
+        self.set_frame = self.animator.lock().unwrap().get_cur_frame();
+        self.next_animator = Some(Animator::run(&self.parent_path, txt));
+    }
+
+    fn has_frames(&self) -> bool {
+        self.debugger.stopped_at().is_some() || self.animator.lock().unwrap().success()
+    }
+
     fn export(&mut self) -> TerminalResult<()> {
-        if let Some((rx, handle)) = start_encoding_thread(self.export_type, self.gl.clone(), self.export_path.clone(), self.animator.clone())? {
-            self.rx = Some(rx);
-            self.thread = Some(handle);
-            self.encoding_frames = self.animator.lock().unwrap().frames.len();
-        }
+        let frames = match &self.timeline.selection {
+            Some(selection) => *selection.start()..*selection.end() + 1,
+            None => 0..self.animator.lock().unwrap().frames.len(),
+        };
+        self.encoding_error.clear();
+        self.encoding_job = EncodingJob::start(self.export_type, &self.gl, self.export_path.clone(), self.animator.clone(), frames)?;
         Ok(())
     }
 }
@@ -172,6 +200,8 @@ impl Document for AnimationEditor {
                 animator.set_cur_frame(self.set_frame);
                 animator.display_frame(self.buffer_view.clone());
             }
+            self.timeline.clear(frame_count);
+            self.onion_skin.clear();
             self.first_frame = false;
         }
         if let Some(next) = &self.next_animator {
@@ -179,8 +209,14 @@ impl Document for AnimationEditor {
                 self.animator = next.clone();
                 self.next_animator = None;
                 let animator = &mut self.animator.lock().unwrap();
+                self.debugger.script_finished(animator);
+                if let Some(frame) = self.debugger.stopped_at() {
+                    self.set_frame = frame.saturating_sub(1);
+                }
                 animator.set_cur_frame(self.set_frame);
                 animator.display_frame(self.buffer_view.clone());
+                self.timeline.clear(animator.frames.len());
+                self.onion_skin.clear();
             }
         }
 
@@ -193,7 +229,7 @@ impl Document for AnimationEditor {
                         ui.set_enabled(false);
                     }
 
-                    if self.animator.lock().unwrap().success() {
+                    if self.has_frames() {
                         let animator = &mut self.animator.lock().unwrap();
                         let frame_count = animator.frames.len();
                         if animator.is_playing() {
@@ -269,7 +305,7 @@ impl Document for AnimationEditor {
                     }
                 });
 
-                if self.animator.lock().unwrap().success() {
+                if self.has_frames() {
                     let cur_frame = self.animator.lock().unwrap().get_cur_frame();
 
                     let monitor_settings = if let Some((_, settings, _)) = self.animator.lock().unwrap().frames.get(cur_frame) {
@@ -288,48 +324,56 @@ impl Document for AnimationEditor {
                         id: Some(Id::new(self.id + 20000)),
                         ..Default::default()
                     };
-                    ui.allocate_ui(Vec2::new(ui.available_width(), ui.available_height() - 100.0), |ui| {
+                    self.onion_skin.show_settings(ui);
+                    ui.allocate_ui(Vec2::new(ui.available_width(), ui.available_height() - 100.0 - TIMELINE_HEIGHT), |ui| {
                         self.buffer_view.lock().get_caret_mut().set_is_visible(false);
-                        let (_, _) = show_terminal_area(ui, self.buffer_view.clone(), opt);
+                        let (_, calc) = show_terminal_area(ui, self.buffer_view.clone(), opt);
+                        self.onion_skin.paint(ui, &self.animator, &calc);
                     });
                     ui.add_space(8.0);
+                    self.timeline.show(ui, &self.animator, &self.buffer_view);
+                    ui.add_space(8.0);
                 }
 
-                if let Some(rx) = &self.rx {
-                    if let Ok(x) = rx.recv() {
-                        self.cur_encoding_frame = x;
-                    }
-
-                    ui.label(fl!(
-                        crate::LANGUAGE_LOADER,
-                        "animation_encoding_frame",
-                        cur = self.cur_encoding_frame,
-                        total = self.encoding_frames
-                    ));
-                    ui.add(ProgressBar::new(self.cur_encoding_frame as f32 / self.encoding_frames as f32));
-                    if self.cur_encoding_frame >= self.encoding_frames {
-                        if let Some(thread) = self.thread.take() {
-                            if let Ok(Err(err)) = thread.join() {
-                                log::error!("Error during encoding: {err}");
-                                self.encoding_error = format!("{err}");
-                            }
+                if let Some(job) = &mut self.encoding_job {
+                    let running = match job.update(&self.gl) {
+                        Ok(running) => running,
+                        Err(err) => {
+                            log::error!("Error during encoding: {err}");
+                            self.encoding_error = format!("{err}");
+                            false
                         }
-                        self.rx = None;
-                    } else if let Some(thread) = &self.thread {
-                        if thread.is_finished() {
-                            if let Err(err) = self.thread.take().unwrap().join() {
-                                let msg = if let Some(msg) = err.downcast_ref::<&'static str>() {
-                                    msg.to_string()
-                                } else if let Some(msg) = err.downcast_ref::<String>() {
-                                    msg.clone()
-                                } else {
-                                    format!("?{:?}", err)
-                                };
-                                log::error!("Error during encoding: {:?}", msg);
-                                self.encoding_error = format!("Thread aborted: {:?}", msg);
-                            }
-                            self.rx = None;
+                    };
+
+                    let frame_count = job.frame_count();
+                    if job.rendered_frames() < frame_count {
+                        ui.label(fl!(
+                            crate::LANGUAGE_LOADER,
+                            "animation_rendering_frame",
+                            cur = job.rendered_frames() + 1,
+                            total = frame_count
+                        ));
+                    } else {
+                        ui.label(fl!(
+                            crate::LANGUAGE_LOADER,
+                            "animation_encoding_frame",
+                            cur = job.encoded_frames(),
+                            total = frame_count
+                        ));
+                    }
+                    // rendering and encoding run in parallel, both count half
+                    let progress = (job.rendered_frames() + job.encoded_frames()) as f32 / (2 * frame_count) as f32;
+                    ui.add(ProgressBar::new(progress));
+                    let cancel = ui.button(fl!(crate::LANGUAGE_LOADER, "animation_encoding_cancel")).clicked();
+
+                    if !running {
+                        self.encoding_job = None;
+                    } else if cancel {
+                        if let Some(job) = self.encoding_job.take() {
+                            job.cancel(&self.gl);
                         }
+                    } else {
+                        ui.ctx().request_repaint();
                     }
                 } else {
                     ui.horizontal(|ui| {
@@ -358,6 +402,7 @@ impl Document for AnimationEditor {
                             }
                         }
                     });
+                    ENCODERS[self.export_type].show_ui(ui);
 
                     if !self.encoding_error.is_empty() {
                         ui.colored_label(ui.style().visuals.error_fg_color, RichText::new(&self.encoding_error));
@@ -371,8 +416,13 @@ impl Document for AnimationEditor {
             });
 
         egui::CentralPanel::default().show_inside(ui, |ui| {
+            let mut debug_action = DebugAction::None;
             TopBottomPanel::bottom("code_error_bottom_panel").exact_height(200.).show_inside(ui, |ui| {
-                if !self.animator.lock().unwrap().error.is_empty() {
+                debug_action = self.debugger.show_controls(ui);
+                ui.separator();
+                if self.debugger.stopped_at().is_some() {
+                    self.debugger.show_state(ui, &self.animator.lock().unwrap());
+                } else if !self.animator.lock().unwrap().error.is_empty() {
                     ui.colored_label(ui.style().visuals.error_fg_color, RichText::new(&self.animator.lock().unwrap().error).small());
                 } else {
                     egui::ScrollArea::vertical().max_width(f32::INFINITY).show(ui, |ui| {
@@ -399,13 +449,17 @@ impl Document for AnimationEditor {
                 .with_syntax(highlighting::lua())
                 .with_numlines(true)
                 .show(ui, &mut self.txt);
+            if let Some(line) = self.debugger.get_error_line(&self.animator.lock().unwrap().error) {
+                highlighting::paint_error_marker(ui, &r, &self.txt, line);
+            }
+            match debug_action {
+                DebugAction::None => {}
+                DebugAction::Run => self.run_script(true),
+                DebugAction::Continue => self.run_script(false),
+            }
             if self.shedule_update && self.last_update.elapsed().as_millis() > 1000 {
                 self.shedule_update = false;
-
-                let path = self.parent_path.clone();
-                let txt = self.txt.clone();
-                self.set_frame = self.animator.lock().unwrap().get_cur_frame();
-                self.next_animator = Some(Animator::run(&path, txt));
+                self.run_script(true);
             }
 
             if let Some(range) = r.cursor_range {
@@ -423,7 +477,7 @@ impl Document for AnimationEditor {
         });
 
         let buffer_view = self.buffer_view.clone();
-        if self.animator.lock().unwrap().success() {
+        if self.has_frames() {
             self.animator.lock().unwrap().update_frame(buffer_view);
         }
         message
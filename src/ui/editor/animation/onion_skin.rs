@@ -0,0 +1,128 @@
+use std::{collections::HashMap, sync::Arc};
+
+use eframe::{
+    egui::{self, color_picker, Color32, ColorImage, DragValue, Rect, Slider, TextureHandle, TextureOptions},
+    epaint::pos2,
+};
+use i18n_embed_fl::fl;
+use icy_engine::Buffer;
+use icy_engine_egui::{animations::Animator, TerminalCalc};
+
+use crate::util::render::{render_to_image, RenderOptions};
+
+const MAX_FRAMES: usize = 10;
+
+/// Blends the neighbouring frames over the preview so frame by frame animations can be aligned.
+pub struct OnionSkin {
+    pub enabled: bool,
+    pub previous_frames: usize,
+    pub next_frames: usize,
+    pub opacity: f32,
+    pub previous_tint: Color32,
+    pub next_tint: Color32,
+
+    textures: HashMap<usize, TextureHandle>,
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            previous_frames: 1,
+            next_frames: 0,
+            opacity: 0.3,
+            previous_tint: Color32::from_rgb(255, 96, 96),
+            next_tint: Color32::from_rgb(96, 160, 255),
+            textures: HashMap::new(),
+        }
+    }
+}
+
+impl OnionSkin {
+    /// Drops the rendered frames, needs to be called when the script ran again.
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.enabled, fl!(crate::LANGUAGE_LOADER, "animation_onion_skin"));
+            ui.add_enabled_ui(self.enabled, |ui| {
+                ui.label(fl!(crate::LANGUAGE_LOADER, "animation_onion_skin_previous"));
+                ui.add(DragValue::new(&mut self.previous_frames).clamp_range(0..=MAX_FRAMES));
+                color_picker::color_edit_button_srgba(ui, &mut self.previous_tint, color_picker::Alpha::Opaque);
+                ui.label(fl!(crate::LANGUAGE_LOADER, "animation_onion_skin_next"));
+                ui.add(DragValue::new(&mut self.next_frames).clamp_range(0..=MAX_FRAMES));
+                color_picker::color_edit_button_srgba(ui, &mut self.next_tint, color_picker::Alpha::Opaque);
+                ui.add(Slider::new(&mut self.opacity, 0.0..=1.0).text(fl!(crate::LANGUAGE_LOADER, "animation_onion_skin_opacity")));
+            });
+        });
+    }
+
+    /// Paints the neighbouring frames over the buffer area of the preview, frames further away get fainter.
+    pub fn paint(&mut self, ui: &egui::Ui, animator: &Arc<std::sync::Mutex<Animator>>, calc: &TerminalCalc) {
+        let animator = animator.lock().unwrap();
+        // during playback the neighbours change every frame
+        if !self.enabled || animator.is_playing() {
+            return;
+        }
+        let cur_frame = animator.get_cur_frame();
+        let frame_count = animator.frames.len();
+
+        // furthest frames first so the closer ones end up on top
+        let mut frames = Vec::new();
+        for distance in (1..=self.previous_frames).rev() {
+            if let Some(frame) = cur_frame.checked_sub(distance) {
+                frames.push((frame, distance, self.previous_frames, self.previous_tint));
+            }
+        }
+        for distance in (1..=self.next_frames).rev() {
+            if cur_frame + distance < frame_count {
+                frames.push((cur_frame + distance, distance, self.next_frames, self.next_tint));
+            }
+        }
+        self.textures.retain(|frame, _| frames.iter().any(|(f, _, _, _)| f == frame));
+
+        let painter = ui.painter_at(calc.buffer_rect);
+        for (frame, distance, count, tint) in frames {
+            if !self.textures.contains_key(&frame) {
+                if let Some(texture) = create_texture(ui.ctx(), &animator.frames[frame].0) {
+                    self.textures.insert(frame, texture);
+                }
+            }
+            let Some(texture) = self.textures.get(&frame) else {
+                continue;
+            };
+            let alpha = self.opacity * (count + 1 - distance) as f32 / count as f32;
+            let min = calc.buffer_rect.min - calc.char_scroll_position * calc.scale;
+            let rect = Rect::from_min_size(min, texture.size_vec2() * calc.scale);
+            painter.image(
+                texture.id(),
+                rect,
+                Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+                tint.linear_multiply(alpha),
+            );
+        }
+    }
+}
+
+fn create_texture(ctx: &egui::Context, buffer: &Buffer) -> Option<TextureHandle> {
+    // the preview applies the aspect ratio itself through its scale
+    let options = RenderOptions {
+        use_aspect_ratio: false,
+        ..RenderOptions::from_buffer(buffer)
+    };
+    let img = match render_to_image(buffer, &options) {
+        Ok(img) => img,
+        Err(err) => {
+            log::error!("Error rendering onion skin frame: {err}");
+            return None;
+        }
+    };
+    let size = [img.width() as usize, img.height() as usize];
+    if size[0] == 0 || size[1] == 0 {
+        return None;
+    }
+    let color_image = ColorImage::from_rgba_unmultiplied(size, img.as_raw());
+    Some(ctx.load_texture("onion_skin_frame", color_image, TextureOptions::NEAREST))
+}
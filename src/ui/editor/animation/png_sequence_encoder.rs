@@ -0,0 +1,69 @@
+use std::{fs, path::Path, sync::mpsc::Sender};
+
+use image::RgbaImage;
+use serde::Serialize;
+
+use crate::TerminalResult;
+
+use super::encoding::{AnimationEncoder, FrameStream};
+
+/// Writes every frame as numbered png next to a json file with the frame timing, for external video tools.
+pub struct PngSequenceEncoder {}
+
+#[derive(Serialize)]
+struct FrameSequence {
+    width: usize,
+    height: usize,
+    frames: Vec<FrameInfo>,
+}
+
+#[derive(Serialize)]
+struct FrameInfo {
+    file: String,
+    /// Delay in ms
+    delay: u32,
+}
+
+impl AnimationEncoder for PngSequenceEncoder {
+    fn label(&self) -> String {
+        "PNG sequence".to_string()
+    }
+    fn extension(&self) -> String {
+        "json".to_string()
+    }
+
+    fn encode(&self, path: &Path, frames: FrameStream, width: usize, height: usize, sender: Sender<usize>) -> TerminalResult<()> {
+        let Some(stem) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else {
+            return Err(anyhow::anyhow!("invalid file name"));
+        };
+        let dir = path.parent().unwrap_or(Path::new(""));
+
+        let mut sequence = FrameSequence {
+            width,
+            height,
+            frames: Vec::new(),
+        };
+        let write_frames = || -> TerminalResult<()> {
+            for (frame_idx, frame) in frames.enumerate() {
+                sender.send(frame_idx)?;
+                let (data, duration) = frame?;
+                let Some(img) = RgbaImage::from_raw(width as u32, height as u32, data) else {
+                    return Err(anyhow::anyhow!("Frame {frame_idx} has an invalid size"));
+                };
+                let file = format!("{stem}_{:04}.png", frame_idx + 1);
+                img.save(dir.join(&file))?;
+                sequence.frames.push(FrameInfo { file, delay: duration });
+            }
+            Ok(())
+        };
+        if let Err(err) = write_frames() {
+            // a partial sequence is useless
+            for frame in &sequence.frames {
+                let _ = fs::remove_file(dir.join(&frame.file));
+            }
+            return Err(err);
+        }
+        fs::write(path, serde_json::to_string_pretty(&sequence)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use std::{collections::HashMap, ops::RangeInclusive, sync::Arc};
+
+use eframe::{
+    egui::{self, Align2, Color32, ColorImage, FontId, Rect, Rounding, Sense, Stroke, TextureHandle, TextureOptions},
+    epaint::{mutex::Mutex, pos2, Vec2},
+};
+use i18n_embed_fl::fl;
+use icy_engine::Buffer;
+use icy_engine_egui::{animations::Animator, BufferView};
+
+use crate::util::render::{render_to_image, RenderOptions};
+
+const THUMBNAIL_WIDTH: f32 = 80.0;
+const THUMBNAIL_HEIGHT: f32 = 50.0;
+const FRAME_WIDTH: f32 = THUMBNAIL_WIDTH + 8.0;
+const FRAME_HEIGHT: f32 = THUMBNAIL_HEIGHT + 24.0;
+pub const TIMELINE_HEIGHT: f32 = FRAME_HEIGHT + 40.0;
+
+/// Strip of frame thumbnails below the preview, click seeks and shift click selects the frames to export.
+#[derive(Default)]
+pub struct Timeline {
+    thumbnails: HashMap<usize, TextureHandle>,
+    pub selection: Option<RangeInclusive<usize>>,
+}
+
+impl Timeline {
+    /// Drops the thumbnails, needs to be called when the script ran again.
+    pub fn clear(&mut self, frame_count: usize) {
+        self.thumbnails.clear();
+        if let Some(selection) = &self.selection {
+            if *selection.end() >= frame_count {
+                self.selection = None;
+            }
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, animator: &Arc<std::sync::Mutex<Animator>>, buffer_view: &Arc<Mutex<BufferView>>) {
+        let mut animator = animator.lock().unwrap();
+        let cur_frame = animator.get_cur_frame();
+        let follow_playback = animator.is_playing();
+        let shift = ui.input(|i| i.modifiers.shift);
+        let mut seek = None;
+
+        egui::ScrollArea::horizontal()
+            .id_source("animation_timeline")
+            .max_height(FRAME_HEIGHT + 16.0)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for (i, (buffer, _, delay)) in animator.frames.iter().enumerate() {
+                        let (rect, response) = ui.allocate_exact_size(Vec2::new(FRAME_WIDTH, FRAME_HEIGHT), Sense::click());
+                        if follow_playback && i == cur_frame {
+                            ui.scroll_to_rect(rect, None);
+                        }
+                        // thumbnails get rendered on demand, long animations have thousands of frames
+                        if !ui.is_rect_visible(rect) {
+                            continue;
+                        }
+
+                        let visuals = ui.visuals();
+                        if self.selection.as_ref().map(|s| s.contains(&i)).unwrap_or(false) {
+                            ui.painter().rect_filled(rect, Rounding::same(4.0), visuals.selection.bg_fill);
+                        } else if response.hovered() {
+                            ui.painter().rect_filled(rect, Rounding::same(4.0), visuals.widgets.hovered.bg_fill);
+                        }
+                        if i == cur_frame {
+                            ui.painter()
+                                .rect_stroke(rect, Rounding::same(4.0), Stroke::new(2.0, visuals.strong_text_color()));
+                        }
+
+                        let thumbnail_rect = Rect::from_min_size(rect.min + Vec2::new(4.0, 4.0), Vec2::new(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT));
+                        if !self.thumbnails.contains_key(&i) {
+                            if let Some(texture) = create_thumbnail(ui.ctx(), buffer) {
+                                self.thumbnails.insert(i, texture);
+                            }
+                        }
+                        if let Some(texture) = self.thumbnails.get(&i) {
+                            let size = texture.size_vec2();
+                            let image_rect = Rect::from_center_size(thumbnail_rect.center(), size);
+                            ui.painter()
+                                .image(texture.id(), image_rect, Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)), Color32::WHITE);
+                        }
+
+                        let text_color = visuals.text_color();
+                        ui.painter().text(
+                            pos2(rect.left() + 4.0, rect.bottom() - 4.0),
+                            Align2::LEFT_BOTTOM,
+                            (i + 1).to_string(),
+                            FontId::proportional(12.0),
+                            text_color,
+                        );
+                        ui.painter().text(
+                            pos2(rect.right() - 4.0, rect.bottom() - 4.0),
+                            Align2::RIGHT_BOTTOM,
+                            format!("{delay}ms"),
+                            FontId::proportional(12.0),
+                            text_color,
+                        );
+
+                        if response.clicked() {
+                            if shift {
+                                self.selection = Some(cur_frame.min(i)..=cur_frame.max(i));
+                            } else {
+                                seek = Some(i);
+                            }
+                        }
+                    }
+                });
+            });
+
+        ui.horizontal(|ui| {
+            if let Some(selection) = self.selection.clone() {
+                ui.label(fl!(
+                    crate::LANGUAGE_LOADER,
+                    "animation_timeline_selection",
+                    from = selection.start() + 1,
+                    to = selection.end() + 1
+                ));
+                if ui.button(fl!(crate::LANGUAGE_LOADER, "animation_timeline_clear_selection")).clicked() {
+                    self.selection = None;
+                }
+            } else {
+                ui.small(fl!(crate::LANGUAGE_LOADER, "animation_timeline_hint"));
+            }
+        });
+
+        if let Some(frame) = seek {
+            animator.set_cur_frame(frame);
+            animator.display_frame(buffer_view.clone());
+        }
+    }
+}
+
+fn create_thumbnail(ctx: &egui::Context, buffer: &Buffer) -> Option<TextureHandle> {
+    let img = match render_to_image(buffer, &RenderOptions::default()) {
+        Ok(img) => img,
+        Err(err) => {
+            log::error!("Error rendering thumbnail: {err}");
+            return None;
+        }
+    };
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let scale = (THUMBNAIL_WIDTH / width as f32).min(THUMBNAIL_HEIGHT / height as f32);
+    let thumbnail = image::imageops::thumbnail(&img, ((width as f32 * scale) as u32).max(1), ((height as f32 * scale) as u32).max(1));
+    let size = [thumbnail.width() as usize, thumbnail.height() as usize];
+    let color_image = ColorImage::from_rgba_unmultiplied(size, thumbnail.as_raw());
+    Some(ctx.load_texture("animation_thumbnail", color_image, TextureOptions::LINEAR))
+}
@@ -0,0 +1,65 @@
+-- Tweening helpers of the animation API, they get put in front of the first line of every animation script.
+-- The lines get joined, so no trailing comments in here.
+
+local __tween_easings = {
+    linear = function(t) return t end,
+    ease_in = function(t) return t * t end,
+    ease_out = function(t) return t * (2 - t) end,
+    ease_in_out = function(t)
+        if t < 0.5 then return 2 * t * t end
+        return -1 + (4 - 2 * t) * t
+    end,
+    bounce = function(t)
+        if t < 1 / 2.75 then return 7.5625 * t * t end
+        if t < 2 / 2.75 then t = t - 1.5 / 2.75 return 7.5625 * t * t + 0.75 end
+        if t < 2.5 / 2.75 then t = t - 2.25 / 2.75 return 7.5625 * t * t + 0.9375 end
+        t = t - 2.625 / 2.75
+        return 7.5625 * t * t + 0.984375
+    end,
+}
+
+local function __tween_get_easing(name, level)
+    local easing = __tween_easings[name or "linear"]
+    if easing == nil then
+        error("unknown easing '" .. tostring(name) .. "' (linear, ease_in, ease_out, ease_in_out, bounce)", level + 1)
+    end
+    return easing
+end
+
+local function __tween_lerp(a, b, t)
+    if b == nil then return a end
+    return math.floor(a + (b - a) * t + 0.5)
+end
+
+function ease(name, t)
+    return __tween_get_easing(name, 2)(t)
+end
+
+function tween(buf, options)
+    local frames = options.frames or 1
+    if frames < 1 then error("tween needs at least one frame", 2) end
+    local easing = __tween_get_easing(options.easing, 2)
+    if options.delay ~= nil then set_delay(options.delay) end
+
+    local layers = {}
+    for layer, target in pairs(options.layers or {}) do
+        local x, y = buf:get_layer_position(layer)
+        layers[#layers + 1] = { layer = layer, x = x, y = y, target = target }
+    end
+
+    for frame = 1, frames do
+        local t = easing(frame / frames)
+        for _, l in ipairs(layers) do
+            buf:set_layer_position(l.layer, __tween_lerp(l.x, l.target.x, t), __tween_lerp(l.y, l.target.y, t))
+            if l.target.visible ~= nil and t >= 0.5 then buf:set_layer_visible(l.layer, l.target.visible) end
+        end
+        if options.on_frame ~= nil then options.on_frame(frame) end
+        next_frame(buf)
+    end
+end
+
+function keyframes(buf, list)
+    for _, options in ipairs(list) do
+        tween(buf, options)
+    end
+end
@@ -0,0 +1,90 @@
+const TWEEN_LIBRARY: &str = include_str!("tween.lua");
+
+/// Puts the tweening helpers in front of the script. They're joined to a single line that shares the first line
+/// of the script, so the line numbers of errors stay the same.
+pub fn add_animation_library(txt: &str) -> String {
+    let library: Vec<&str> = TWEEN_LIBRARY.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with("--")).collect();
+    format!("{}; {txt}", library.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread, time::Duration};
+
+    use icy_engine_egui::animations::Animator;
+    use mlua::Lua;
+
+    use super::*;
+
+    fn run_animation(txt: &str) -> Arc<std::sync::Mutex<Animator>> {
+        let animator = Animator::run(&None, add_animation_library(txt));
+        for _ in 0..200 {
+            {
+                let animator = animator.lock().unwrap();
+                assert!(animator.error.is_empty(), "{}", animator.error);
+                if animator.success() {
+                    break;
+                }
+            }
+            thread::sleep(Duration::from_millis(25));
+        }
+        assert!(animator.lock().unwrap().success());
+        animator
+    }
+
+    fn get_error(txt: &str) -> String {
+        let lua = Lua::new();
+        lua.load(add_animation_library(txt)).set_name("=anim").exec().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_library_keeps_line_numbers() {
+        let err = get_error("local a = 1\n\nerror(\"stop\")");
+        assert!(err.contains("anim:3: stop"), "{err}");
+
+        // errors of the helpers point to the calling line of the script
+        let err = get_error("\ntween({}, { easing = \"foo\" })");
+        assert!(err.contains("anim:2: unknown easing 'foo'"), "{err}");
+    }
+
+    #[test]
+    fn test_tween_layers() {
+        let animator = run_animation(
+            r#"
+            local buf = new_buffer(20, 10)
+            tween(buf, { frames = 4, delay = 50, layers = { [0] = { x = 8, y = 4 } } })
+            "#,
+        );
+        let animator = animator.lock().unwrap();
+        let positions: Vec<(i32, i32)> = animator
+            .frames
+            .iter()
+            .map(|(buf, _, _)| {
+                let offset = buf.layers[0].get_offset();
+                (offset.x, offset.y)
+            })
+            .collect();
+        assert_eq!(vec![(2, 1), (4, 2), (6, 3), (8, 4)], positions);
+        assert!(animator.frames.iter().all(|(_, _, delay)| *delay == 50));
+    }
+
+    #[test]
+    fn test_keyframes_easing() {
+        let animator = run_animation(
+            r#"
+            local buf = new_buffer(20, 10)
+            keyframes(buf, {
+                { frames = 2, easing = "ease_in", layers = { [0] = { x = 10 } } },
+                { frames = 2, easing = "ease_out", layers = { [0] = { x = 0, visible = false } } },
+            })
+            "#,
+        );
+        let animator = animator.lock().unwrap();
+        let layers: Vec<(i32, bool)> = animator
+            .frames
+            .iter()
+            .map(|(buf, _, _)| (buf.layers[0].get_offset().x, buf.layers[0].is_visible))
+            .collect();
+        assert_eq!(vec![(3, true), (10, true), (3, false), (0, false)], layers);
+    }
+}
@@ -0,0 +1,45 @@
+use std::{fs, path::Path, sync::mpsc::Sender};
+
+use webp_animation::{EncoderOptions, EncodingConfig, EncodingType};
+
+use crate::TerminalResult;
+
+use super::encoding::{AnimationEncoder, FrameStream};
+
+pub struct WebpEncoder {}
+
+impl AnimationEncoder for WebpEncoder {
+    fn label(&self) -> String {
+        "WebP".to_string()
+    }
+    fn extension(&self) -> String {
+        "webp".to_string()
+    }
+
+    fn encode(&self, path: &Path, frames: FrameStream, width: usize, height: usize, sender: Sender<usize>) -> TerminalResult<()> {
+        // lossless keeps the exact colors of the rgb palettes
+        let options = EncoderOptions {
+            encoding_config: Some(EncodingConfig {
+                encoding_type: EncodingType::Lossless,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut encoder = webp_animation::Encoder::new_with_options((width as u32, height as u32), options).map_err(webp_error)?;
+
+        let mut time = 0;
+        for (frame_idx, frame) in frames.enumerate() {
+            sender.send(frame_idx)?;
+            let (data, duration) = frame?;
+            encoder.add_frame(&data, time).map_err(webp_error)?;
+            time += duration as i32;
+        }
+        let webp_data = encoder.finalize(time).map_err(webp_error)?;
+        fs::write(path, &*webp_data)?;
+        Ok(())
+    }
+}
+
+fn webp_error(err: webp_animation::Error) -> anyhow::Error {
+    anyhow::anyhow!("WebP encoding failed: {err:?}")
+}
@@ -54,6 +54,8 @@ pub struct AnsiEditor {
     //pub attr_changed: std::boxed::Box<dyn Fn(TextAttribute)>
     pub request_focus: bool,
     pub color_mode: ColorMode,
+    /// Name of the tool that finished a click or drag, picked up for the plugin tool use event.
+    pub tool_stroke_finished: Option<String>,
 }
 
 impl UndoHandler for AnsiEditor {
@@ -240,6 +242,7 @@ impl AnsiEditor {
             last_selected_tool: 0,
             request_focus: false,
             color_mode: ColorMode::Both,
+            tool_stroke_finished: None,
         }
     }
 
@@ -626,6 +629,7 @@ impl AnsiEditor {
                     if message.is_none() {
                         *message = msg;
                     }
+                    self.tool_stroke_finished = Some(cur_tool.tool_name());
                 }
             }
         }
@@ -700,6 +704,7 @@ impl AnsiEditor {
             if msg.is_some() {
                 *message = msg;
             }
+            self.tool_stroke_finished = Some(cur_tool.tool_name());
 
             self.drag_started = false;
         }
@@ -6,9 +6,12 @@ use std::{
 };
 
 use crate::{
-    add_child, model::Tool, util::autosave, AnsiEditor, AskCloseFileDialog, BitFontEditor, ChannelToolWindow, CharFontEditor, Commands, Document,
-    DocumentBehavior, DocumentTab, LayerToolWindow, Message, MinimapToolWindow, ModalDialog, SettingsDialog, ToolBehavior, ToolTab, TopBar, KEYBINDINGS,
-    SETTINGS,
+    add_child,
+    model::Tool,
+    plugins::{EditTarget, Plugin, PluginEvent},
+    util::autosave,
+    AnsiEditor, AskCloseFileDialog, BitFontEditor, ChannelToolWindow, CharFontEditor, Commands, Document, DocumentBehavior, DocumentTab, LayerToolWindow,
+    LuaConsoleToolWindow, Message, MinimapToolWindow, ModalDialog, SettingsDialog, ToolBehavior, ToolTab, TopBar, KEYBINDINGS, SETTINGS,
 };
 use directories::UserDirs;
 use eframe::egui::{Button, PointerButton};
@@ -20,7 +23,7 @@ use egui::mutex::Mutex;
 use egui_tiles::{Container, TileId};
 use glow::Context;
 use i18n_embed_fl::fl;
-use icy_engine::{BitFont, Buffer, EngineResult, Palette, TextAttribute, TheDrawFont};
+use icy_engine::{BitFont, Buffer, EngineResult, Palette, Rectangle, TextAttribute, TheDrawFont};
 
 pub struct MainWindow<'a> {
     pub document_tree: egui_tiles::Tree<DocumentTab>,
@@ -47,6 +50,8 @@ pub struct MainWindow<'a> {
     pub commands: Vec<Box<Commands>>,
     pub last_command_update: Instant,
     pub is_fullscreen: bool,
+    /// Editor id and selection, used to detect selection changes for plugins.
+    last_selection: Option<(usize, Option<Rectangle>)>,
 
     pub in_open_file_mode: bool,
     pub open_file_window: view_library::MainWindow<'a>,
@@ -73,7 +78,7 @@ impl<'a> MainWindow<'a> {
         fnt.install_watcher();
         egui_extras::install_image_loaders(&cc.egui_ctx);
 
-        let tools: Vec<Box<dyn Tool>> = vec![
+        let mut tools: Vec<Box<dyn Tool>> = vec![
             Box::<crate::model::paste_tool::PasteTool>::default(),
             Box::<crate::model::click_imp::ClickTool>::default(),
             Box::<crate::model::select_imp::SelectTool>::default(),
@@ -91,6 +96,10 @@ impl<'a> MainWindow<'a> {
             Box::new(fnt),
             Box::<crate::model::move_layer_imp::MoveLayer>::default(),
         ];
+        let (lua_tools, tool_errors) = crate::model::lua_tool::LuaTool::read_tools_directory();
+        for tool in lua_tools {
+            tools.push(Box::new(tool));
+        }
 
         let ctx: &egui::Context = &cc.egui_ctx;
 
@@ -118,9 +127,10 @@ impl<'a> MainWindow<'a> {
         let layers = tool_tree.tiles.insert_pane(ToolTab::new(LayerToolWindow::new(gl.clone())));
         let channels = tool_tree.tiles.insert_pane(ToolTab::new(ChannelToolWindow::default()));
         let minimap = tool_tree.tiles.insert_pane(ToolTab::new(MinimapToolWindow::new(gl.clone())));
+        let lua_console = tool_tree.tiles.insert_pane(ToolTab::new(LuaConsoleToolWindow::default()));
 
         let tab = tool_tree.tiles.insert_tab_tile(vec![minimap]);
-        let tab2 = tool_tree.tiles.insert_tab_tile(vec![layers, channels]);
+        let tab2 = tool_tree.tiles.insert_tab_tile(vec![layers, channels, lua_console]);
         let vert_id = tool_tree.tiles.insert_vertical_tile(vec![tab, tab2]);
         if let Some(egui_tiles::Tile::Container(Container::Linear(linear))) = tool_tree.tiles.get_mut(vert_id) {
             linear.shares.set_share(tab, 3.0);
@@ -129,12 +139,14 @@ impl<'a> MainWindow<'a> {
 
         tool_tree.root = Some(vert_id);
         let open_file_window = view_library::MainWindow::new(&gl, None);
+        let plugin_errors = Plugin::read_plugin_directory();
+        Plugin::install_watcher(cc.egui_ctx.clone());
         let mut c = Box::<Commands>::default();
         unsafe {
             c.apply_key_bindings(&KEYBINDINGS.key_bindings);
         }
         let settings_dialog = SettingsDialog::new(ctx, &gl);
-        MainWindow {
+        let mut main_window = MainWindow {
             document_behavior: DocumentBehavior::new(Arc::new(Mutex::new(tools))),
             tool_behavior: ToolBehavior::default(),
             toasts: egui_notify::Toasts::default(),
@@ -156,7 +168,59 @@ impl<'a> MainWindow<'a> {
             show_settings: false,
             settings_dialog,
             last_command_update: Instant::now(),
+            last_selection: None,
             current_id: None,
+        };
+        main_window.show_plugin_errors(plugin_errors);
+        for err in tool_errors {
+            main_window
+                .toasts
+                .error(fl!(crate::LANGUAGE_LOADER, "error-load-tool", error = err))
+                .set_duration(Some(Duration::from_secs(10)));
+        }
+        main_window
+    }
+
+    /// Fires the selection change and tool use events of the active editor.
+    fn fire_editor_plugin_events(&mut self, ctx: &egui::Context) {
+        let Some(doc) = self.get_active_document() else {
+            return;
+        };
+        let mut events = Vec::new();
+        let target = {
+            let mut doc = doc.lock();
+            let Some(editor) = doc.get_ansi_editor_mut() else {
+                return;
+            };
+            if let Some(tool) = editor.tool_stroke_finished.take() {
+                events.push((PluginEvent::ToolUse, Some(tool)));
+            }
+            if PluginEvent::SelectionChange.has_callbacks() && !ctx.input(|i| i.pointer.any_down()) {
+                let selection = editor.buffer_view.lock().get_edit_state().get_selection().map(|sel| sel.as_rectangle());
+                if let Some((id, last_selection)) = &self.last_selection {
+                    if *id == editor.id && *last_selection != selection {
+                        events.push((PluginEvent::SelectionChange, None));
+                    }
+                }
+                self.last_selection = Some((editor.id, selection));
+            }
+            EditTarget::View(editor.buffer_view.clone())
+        };
+
+        for (event, arg) in events {
+            if event.has_callbacks() {
+                if let Err(err) = event.fire(&target, arg) {
+                    self.toasts.error(err.to_string()).set_duration(Some(Duration::from_secs(5)));
+                }
+            }
+        }
+    }
+
+    fn show_plugin_errors(&mut self, errors: Vec<String>) {
+        for err in errors {
+            self.toasts
+                .error(fl!(crate::LANGUAGE_LOADER, "error-load-plugin", error = err))
+                .set_duration(Some(Duration::from_secs(10)));
         }
     }
 
@@ -213,6 +277,9 @@ impl<'a> MainWindow<'a> {
                 let id = self.create_id();
                 buf.is_terminal_buffer = false;
                 let editor = AnsiEditor::new(&self.gl, id, buf);
+                if let Err(err) = PluginEvent::Open.fire(&EditTarget::View(editor.buffer_view.clone()), Some(path.to_string_lossy().to_string())) {
+                    self.toasts.error(err.to_string()).set_duration(Some(Duration::from_secs(5)));
+                }
                 add_child(&mut self.document_tree, Some(full_path), Box::new(editor));
             }
             Err(err) => {
@@ -590,6 +657,9 @@ impl<'a> eframe::App for MainWindow<'a> {
             return;
         }
 
+        // a running plugin edits the buffer from its own thread
+        let is_blocked = self.modal_dialog.as_ref().map_or(false, |dialog| dialog.is_blocking());
+
         let msg = self.show_top_bar(ctx, frame);
         self.update_title(frame);
         self.handle_message(msg);
@@ -604,6 +674,7 @@ impl<'a> eframe::App for MainWindow<'a> {
                 ..Default::default()
             })
             .show_animated(ctx, self.left_panel, |ui| {
+                ui.set_enabled(!is_blocked);
                 ui.add_space(8.0);
                 let mut msg = None;
 
@@ -687,7 +758,15 @@ impl<'a> eframe::App for MainWindow<'a> {
             .exact_width(324.0)
             .resizable(false)
             .show_animated(ctx, self.right_panel, |ui| {
+                ui.set_enabled(!is_blocked);
                 self.tool_behavior.active_document = self.get_active_document();
+                let mut open_editors = Vec::new();
+                self.enumerate_documents(|_, tab| {
+                    if let Some(editor) = tab.doc.lock().get_ansi_editor() {
+                        open_editors.push(editor.id);
+                    }
+                });
+                self.tool_behavior.open_editors = open_editors;
                 self.tool_tree.ui(&mut self.tool_behavior, ui);
                 self.tool_behavior.active_document = None;
                 let msg = self.tool_behavior.message.take();
@@ -701,6 +780,7 @@ impl<'a> eframe::App for MainWindow<'a> {
             })
             .show(ctx, |ui| {
                 ui.set_width(ui.available_width() - 250.0);
+                ui.set_enabled(!is_blocked);
                 self.document_tree.ui(&mut self.document_behavior, ui);
 
                 if self.document_behavior.get_selected_tool() != PASTE_TOOL {
@@ -792,9 +872,13 @@ impl<'a> eframe::App for MainWindow<'a> {
         }
 
         let mut msg = self.document_behavior.message.take();
-        self.commands[0].check(ctx, &mut msg);
+        if !is_blocked {
+            self.commands[0].check(ctx, &mut msg);
+        }
         self.handle_message(msg);
-        self.handle_message(read_outline_keys(ctx));
+        if !is_blocked {
+            self.handle_message(read_outline_keys(ctx));
+        }
 
         ctx.input(|i| {
             for f in &i.raw.dropped_files {
@@ -831,6 +915,17 @@ impl<'a> eframe::App for MainWindow<'a> {
             }
         }
 
+        if !is_blocked {
+            self.fire_editor_plugin_events(ctx);
+        }
+
+        if Plugin::plugin_directory_changed() {
+            let errors = Plugin::read_plugin_directory();
+            unsafe {
+                self.commands[0].apply_key_bindings(&KEYBINDINGS.key_bindings);
+            }
+            self.show_plugin_errors(errors);
+        }
 
         if let Some(id) = focus {
             if ctx.memory(|r| r.focus()).is_none() {
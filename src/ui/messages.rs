@@ -9,6 +9,7 @@ use egui::mutex::Mutex;
 use icy_engine::{util::pop_data, BitFont, EngineResult, IceMode, Layer, PaletteMode, SauceData, Size, TextAttribute, TextPane, TheDrawFont};
 
 use crate::{
+    plugins::{ExecutionControl, PLUGIN_TIME_LIMIT},
     util::autosave::{self},
     AnsiEditor, DocumentOptions, MainWindow, NewFileDialog, SaveFileDialog, SelectCharacterDialog, SelectOutlineDialog, Settings, MRU_FILES, PLUGINS, SETTINGS,
 };
@@ -133,6 +134,7 @@ pub enum Message {
     ToggleLayerBorders,
     ToggleLineNumbers,
     RunPlugin(usize),
+    ExecutePlugin(usize),
     OpenPluginDirectory,
     SelectPreviousTool,
     NextFgColor,
@@ -920,15 +922,29 @@ impl<'a> MainWindow<'a> {
                 SETTINGS.show_line_numbers = !SETTINGS.show_line_numbers;
             },
             Message::RunPlugin(i) => {
-                self.run_editor_command(i, |window, editor, i| {
-                    let mut msg = None;
-                    unsafe {
-                        if let Err(err) = PLUGINS[i].run_plugin(window, editor) {
-                            msg = Some(Message::ShowError(format!("Error running plugin: {err}")));
+                if unsafe { PLUGINS.get(i).map_or(true, |p| p.parameters.is_empty()) } {
+                    self.handle_message(Some(Message::ExecutePlugin(i)));
+                } else if let Some(doc) = self.get_active_document() {
+                    if let Some(editor) = doc.lock().get_ansi_editor() {
+                        let view = editor.buffer_view.clone();
+                        self.open_dialog(crate::PluginParameterDialog::new(i, view.lock().get_buffer()));
+                    }
+                }
+            }
+            Message::ExecutePlugin(i) => {
+                let mut dialog = None;
+                if let Some(doc) = self.get_active_document() {
+                    if let (Some(editor), Some(plugin)) = (doc.lock().get_ansi_editor(), unsafe { PLUGINS.get(i) }) {
+                        let control = ExecutionControl::new(Some(PLUGIN_TIME_LIMIT));
+                        match plugin.start(editor, control.clone()) {
+                            Ok(handle) => dialog = Some(crate::PluginProgressDialog::new(plugin.title.clone(), control, handle)),
+                            Err(err) => self.handle_message(Some(Message::ShowError(format!("Error running plugin: {err}")))),
                         }
                     }
-                    msg
-                });
+                }
+                if let Some(dialog) = dialog {
+                    self.open_dialog(dialog);
+                }
             }
             Message::OpenPluginDirectory => match Settings::get_plugin_directory() {
                 Ok(dir) => {
@@ -55,4 +55,9 @@ pub trait ModalDialog {
     fn commit_self(&self, _window: &mut MainWindow<'_>) -> TerminalResult<Option<Message>> {
         Ok(None)
     }
+
+    /// The editors don't take input and commands while a blocking dialog is open.
+    fn is_blocking(&self) -> bool {
+        false
+    }
 }
@@ -10,7 +10,10 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::{plugins::Plugin, TerminalResult};
+use crate::{
+    plugins::{Plugin, PluginValue},
+    AnsimationSettings, AsciiCastSettings, GifSettings, TerminalResult,
+};
 
 const MAX_RECENT_FILES: usize = 10;
 
@@ -28,6 +31,13 @@ pub struct Settings {
     pub monitor_settings: MonitorSettings,
     pub marker_settings: MarkerSettings,
     pub save_options: SaveOptions,
+
+    #[serde(default)]
+    pub ansimation: AnsimationSettings,
+    #[serde(default)]
+    pub asciicast: AsciiCastSettings,
+    #[serde(default)]
+    pub gif: GifSettings,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -67,6 +77,53 @@ impl KeyBindings {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PluginParameterValues {
+    pub values: Vec<(String, Vec<(String, PluginValue)>)>,
+}
+
+impl PluginParameterValues {
+    pub fn get_plugin_parameters_file() -> TerminalResult<PathBuf> {
+        if let Some(proj_dirs) = ProjectDirs::from("com", "GitHub", "icy_draw") {
+            let dir = proj_dirs.config_dir().join("plugin_parameters.json");
+            return Ok(dir);
+        }
+        Err(IcyDrawError::ErrorCreatingDirectory("plugin_parameters".to_string()).into())
+    }
+
+    pub fn get_values(&self, plugin: &str) -> Option<&Vec<(String, PluginValue)>> {
+        self.values.iter().find(|(p, _)| p == plugin).map(|(_, values)| values)
+    }
+
+    pub fn set_values(&mut self, plugin: &str, values: Vec<(String, PluginValue)>) {
+        if let Some(entry) = self.values.iter_mut().find(|(p, _)| p == plugin) {
+            entry.1 = values;
+        } else {
+            self.values.push((plugin.to_string(), values));
+        }
+        if let Err(err) = self.save() {
+            log::error!("Error saving plugin parameters: {}", err);
+        }
+    }
+
+    pub fn load(path: &PathBuf) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Ok(path) = PluginParameterValues::get_plugin_parameters_file() else {
+            return Ok(());
+        };
+
+        let file = File::create(path)?;
+        let reader = BufWriter::new(file);
+        serde_json::to_writer_pretty(reader, &self)?;
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CharacterSets {
     pub character_sets: Vec<CharSetMapping>,
@@ -248,6 +305,18 @@ impl Settings {
         Err(IcyDrawError::ErrorCreatingDirectory("plugin directory".to_string()).into())
     }
 
+    pub(crate) fn get_tools_directory() -> TerminalResult<PathBuf> {
+        if let Some(proj_dirs) = ProjectDirs::from("com", "GitHub", "icy_draw") {
+            let dir = proj_dirs.config_dir().join("data/tools");
+
+            if !dir.exists() && fs::create_dir_all(&dir).is_err() {
+                return Err(IcyDrawError::ErrorCreatingDirectory(format!("{dir:?}")).into());
+            }
+            return Ok(dir);
+        }
+        Err(IcyDrawError::ErrorCreatingDirectory("tools directory".to_string()).into())
+    }
+
     pub(crate) fn load(path: &PathBuf) -> io::Result<Settings> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
@@ -353,6 +422,8 @@ impl MostRecentlyUsedFiles {
 
 pub static mut PLUGINS: Vec<Plugin> = Vec::new();
 
+pub static mut PLUGIN_PARAMETERS: PluginParameterValues = PluginParameterValues { values: Vec::new() };
+
 pub static mut KEYBINDINGS: KeyBindings = KeyBindings { key_bindings: Vec::new() };
 
 pub static mut CHARACTER_SETS: CharacterSets = CharacterSets { character_sets: Vec::new() };
@@ -365,6 +436,9 @@ pub static mut SETTINGS: Settings = Settings {
     show_layer_borders: true,
     show_line_numbers: false,
     save_options: SaveOptions::new(),
+    ansimation: AnsimationSettings::new(),
+    asciicast: AsciiCastSettings::new(),
+    gif: GifSettings::new(),
     is_dark_mode: None,
     monitor_settings: MonitorSettings {
         use_filter: false,
@@ -18,6 +18,8 @@ impl ToolTab {
 pub struct ToolBehavior {
     pub active_document: Option<Arc<Mutex<Box<dyn Document>>>>,
     pub message: Option<Message>,
+    /// Ids of the ansi editors of all open documents.
+    pub open_editors: Vec<usize>,
 }
 
 impl egui_tiles::Behavior<ToolTab> for ToolBehavior {
@@ -28,6 +30,7 @@ impl egui_tiles::Behavior<ToolTab> for ToolBehavior {
     }
 
     fn pane_ui(&mut self, ui: &mut egui::Ui, _tile_id: egui_tiles::TileId, pane: &mut ToolTab) -> egui_tiles::UiResponse {
+        pane.doc.retain_editors(&self.open_editors);
         let message = pane.doc.show_ui(ui, self.active_document.clone());
         if self.message.is_none() {
             self.message = message;
@@ -51,4 +54,7 @@ pub trait ToolWindow {
     fn get_title(&self) -> String;
 
     fn show_ui(&mut self, ui: &mut egui::Ui, active_document: Option<Arc<Mutex<Box<dyn Document>>>>) -> Option<Message>;
+
+    /// Drops the state kept for editors that are no longer open.
+    fn retain_editors(&mut self, _open_editors: &[usize]) {}
 }
@@ -5,6 +5,8 @@ use eframe::{
 };
 use egui::RichText;
 
+const TOOLS_PER_ROW: usize = 8;
+
 pub fn add_tool_switcher(_ctx: &egui::Context, ui: &mut egui::Ui, arg: &MainWindow<'_>) -> Option<Message> {
     let mut msg = None;
     let spacing = 4.0;
@@ -14,7 +16,8 @@ pub fn add_tool_switcher(_ctx: &egui::Context, ui: &mut egui::Ui, arg: &MainWind
     if tools[arg.document_behavior.get_selected_tool()].is_exclusive() {
         return msg;
     }
-    let (id, back_rect) = ui.allocate_space(Vec2::new(230., 68.0));
+    let rows = (tools.iter().filter(|t| t.is_visible()).count() + TOOLS_PER_ROW - 1) / TOOLS_PER_ROW;
+    let (id, back_rect) = ui.allocate_space(Vec2::new(230., rows.max(2) as f32 * (icon_size + spacing) + spacing));
     let mut pos = back_rect.min + Vec2::new(spacing, spacing);
 
     for i in 0..tools.len() {
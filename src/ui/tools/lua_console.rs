@@ -0,0 +1,223 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc, time::Duration};
+
+use eframe::egui::{self, Key, Modifiers, RichText};
+use egui::mutex::Mutex;
+use egui_code_editor::CodeEditor;
+use i18n_embed_fl::fl;
+use mlua::{Lua, Value, Variadic};
+
+use crate::{
+    plugins::{format_lua_error, run_atomic, setup_lua, EditTarget, ExecutionControl},
+    Document, Message, ToolWindow,
+};
+
+/// Console snippets run on the UI thread, endless loops get aborted after this time.
+const CONSOLE_TIME_LIMIT: Duration = Duration::from_secs(10);
+const CHUNK_NAME: &str = "console";
+const MAX_TABLE_ENTRIES: usize = 16;
+
+enum ConsoleLine {
+    Input(String),
+    Output(String),
+    Error(String),
+}
+
+/// The Lua state of a document, globals defined in the console live as long as the document.
+struct ConsoleState {
+    lua: Lua,
+    lines: Vec<ConsoleLine>,
+    output: Rc<RefCell<Vec<String>>>,
+}
+
+impl ConsoleState {
+    fn new() -> mlua::Result<Self> {
+        let lua = Lua::new();
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let print_output = output.clone();
+        lua.globals().set(
+            "print",
+            lua.create_function(move |_lua, args: Variadic<Value>| {
+                let line: Vec<String> = args.iter().map(to_print_string).collect();
+                print_output.borrow_mut().push(line.join("\t"));
+                Ok(())
+            })?,
+        )?;
+        Ok(Self {
+            lua,
+            lines: Vec::new(),
+            output,
+        })
+    }
+
+    fn eval(&mut self, target: EditTarget, code: &str) {
+        self.lines.push(ConsoleLine::Input(code.to_string()));
+        let result = self.run(target, code);
+        self.lines.extend(self.output.borrow_mut().drain(..).map(ConsoleLine::Output));
+        match result {
+            Ok(values) => {
+                if !values.is_empty() {
+                    self.lines.push(ConsoleLine::Output(values.join(", ")));
+                }
+            }
+            Err(err) => self.lines.push(ConsoleLine::Error(err.to_string())),
+        }
+    }
+
+    fn run(&self, target: EditTarget, code: &str) -> anyhow::Result<Vec<String>> {
+        let control = ExecutionControl::new(Some(CONSOLE_TIME_LIMIT));
+        setup_lua(&self.lua, target.clone(), &control)?;
+
+        run_atomic(&target, fl!(crate::LANGUAGE_LOADER, "undo-lua-console"), || {
+            // like the lua interpreter: expressions are evaluated and their value is shown
+            let function = match self.lua.load(format!("return {code}")).set_name(format!("={CHUNK_NAME}")).into_function() {
+                Ok(function) => function,
+                Err(_) => self
+                    .lua
+                    .load(code)
+                    .set_name(format!("={CHUNK_NAME}"))
+                    .into_function()
+                    .map_err(|err| anyhow::anyhow!("{}", format_lua_error(&err, CHUNK_NAME)))?,
+            };
+            let values: mlua::MultiValue = function.call(()).map_err(|err| anyhow::anyhow!("{}", format_lua_error(&err, CHUNK_NAME)))?;
+            Ok(values.into_iter().map(|value| format_value(&value, 0)).collect())
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct LuaConsoleToolWindow {
+    states: HashMap<usize, ConsoleState>,
+    input: String,
+    input_has_focus: bool,
+}
+
+impl ToolWindow for LuaConsoleToolWindow {
+    fn get_title(&self) -> String {
+        fl!(crate::LANGUAGE_LOADER, "lua_console_tool_title")
+    }
+
+    fn retain_editors(&mut self, open_editors: &[usize]) {
+        self.states.retain(|id, _| open_editors.contains(id));
+    }
+
+    fn show_ui(&mut self, ui: &mut egui::Ui, active_document: Option<Arc<Mutex<Box<dyn Document>>>>) -> Option<Message> {
+        let Some(doc) = active_document else {
+            show_no_document(ui);
+            return None;
+        };
+        let doc = doc.lock();
+        let Some(editor) = doc.get_ansi_editor() else {
+            show_no_document(ui);
+            return None;
+        };
+
+        if !self.states.contains_key(&editor.id) {
+            match ConsoleState::new() {
+                Ok(state) => {
+                    self.states.insert(editor.id, state);
+                }
+                Err(err) => {
+                    ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                    return None;
+                }
+            }
+        }
+        let state = self.states.get_mut(&editor.id).unwrap();
+
+        let run = self.input_has_focus && ui.input_mut(|i| i.consume_key(Modifiers::CTRL, Key::Enter));
+
+        let output_height = (ui.available_height() - 120.0).max(40.0);
+        egui::ScrollArea::vertical()
+            .id_source("lua_console_output")
+            .max_height(output_height)
+            .stick_to_bottom(true)
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for line in &state.lines {
+                    match line {
+                        ConsoleLine::Input(code) => {
+                            let code = code.lines().map(|l| format!("> {l}")).collect::<Vec<_>>().join("\n");
+                            ui.label(RichText::new(code).monospace().weak());
+                        }
+                        ConsoleLine::Output(text) => {
+                            ui.label(RichText::new(text).monospace());
+                        }
+                        ConsoleLine::Error(text) => {
+                            ui.label(RichText::new(text).monospace().color(ui.visuals().error_fg_color));
+                        }
+                    }
+                }
+            });
+        ui.separator();
+
+        let r = CodeEditor::default()
+            .id_source("lua_console_input")
+            .with_rows(3)
+            .with_fontsize(14.0)
+            .with_theme(if ui.style().visuals.dark_mode {
+                egui_code_editor::ColorTheme::GITHUB_DARK
+            } else {
+                egui_code_editor::ColorTheme::GITHUB_LIGHT
+            })
+            .with_syntax(crate::highlighting::lua())
+            .with_numlines(false)
+            .show(ui, &mut self.input);
+        self.input_has_focus = r.response.has_focus();
+
+        let mut run_clicked = false;
+        ui.horizontal(|ui| {
+            run_clicked = ui
+                .button(fl!(crate::LANGUAGE_LOADER, "lua_console_run_button"))
+                .on_hover_text("Ctrl+Enter")
+                .clicked();
+            if ui.button(fl!(crate::LANGUAGE_LOADER, "lua_console_clear_button")).clicked() {
+                state.lines.clear();
+            }
+        });
+
+        if (run || run_clicked) && !self.input.trim().is_empty() {
+            let code = self.input.trim().to_string();
+            state.eval(EditTarget::View(editor.buffer_view.clone()), &code);
+            self.input.clear();
+        }
+        None
+    }
+}
+
+fn show_no_document(ui: &mut egui::Ui) {
+    ui.vertical_centered(|ui| {
+        ui.add_space(8.0);
+        ui.label(RichText::new(fl!(crate::LANGUAGE_LOADER, "no_document_selected")).small());
+    });
+}
+
+fn to_print_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_string_lossy().to_string(),
+        _ => format_value(value, 0),
+    }
+}
+
+fn format_value(value: &Value, depth: usize) -> String {
+    match value {
+        Value::Nil => "nil".to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("{:?}", s.to_string_lossy()),
+        Value::Table(table) if depth == 0 => {
+            let mut entries: Vec<String> = table
+                .clone()
+                .pairs::<Value, Value>()
+                .flatten()
+                .take(MAX_TABLE_ENTRIES + 1)
+                .map(|(key, value)| format!("{} = {}", format_value(&key, depth + 1), format_value(&value, depth + 1)))
+                .collect();
+            if entries.len() > MAX_TABLE_ENTRIES {
+                entries[MAX_TABLE_ENTRIES] = "…".to_string();
+            }
+            format!("{{ {} }}", entries.join(", "))
+        }
+        _ => value.type_name().to_string(),
+    }
+}
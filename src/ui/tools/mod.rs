@@ -8,3 +8,5 @@ mod minimap_view;
 pub use minimap_view::*;
 mod channels;
 pub use channels::*;
+mod lua_console;
+pub use lua_console::*;
@@ -11,7 +11,7 @@ use icy_engine::{
     FontMode, IceMode, PaletteMode,
 };
 
-use crate::{button_with_shortcut, MainWindow, Message, Settings, MRU_FILES, PLUGINS};
+use crate::{button_with_shortcut, plugins::PluginMenu, MainWindow, Message, Settings, MRU_FILES, PLUGINS};
 
 lazy_static::lazy_static! {
     pub static ref DOCK_LEFT_SVG: Image<'static> = Image::new(egui::include_image!("../../data/icons/dock_left.svg"));
@@ -539,12 +539,8 @@ impl<'a> MainWindow<'a> {
                     ui.menu_button(fl!(crate::LANGUAGE_LOADER, "menu-plugins"), |ui| {
                         ui.style_mut().wrap = Some(false);
                         ui.set_min_width(250.0);
-                        for (i, p) in PLUGINS.iter().enumerate() {
-                            if ui.add_enabled(has_buffer, egui::Button::new(p.title.clone()).wrap(false)).clicked() {
-                                result = Some(Message::RunPlugin(i));
-                                ui.close_menu();
-                            }
-                        }
+                        let menu = PluginMenu::build(&PLUGINS);
+                        self.plugin_menu_ui(ui, &menu, &mut result);
 
                         ui.separator();
                         self.commands[0].open_plugin_directory.ui(ui, &mut result);
@@ -582,6 +578,24 @@ impl<'a> MainWindow<'a> {
         result
     }
 
+    fn plugin_menu_ui(&self, ui: &mut Ui, menu: &PluginMenu, result: &mut Option<Message>) {
+        for submenu in &menu.submenus {
+            ui.menu_button(&submenu.title, |ui| {
+                ui.style_mut().wrap = Some(false);
+                ui.set_min_width(250.0);
+                self.plugin_menu_ui(ui, submenu, result);
+            });
+        }
+        if !menu.submenus.is_empty() && !menu.plugins.is_empty() {
+            ui.separator();
+        }
+        for i in &menu.plugins {
+            if let Some((_, cmd)) = self.commands[0].plugin_commands.get(*i) {
+                cmd.ui(ui, result);
+            }
+        }
+    }
+
     fn top_bar_ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
             let right = medium_toggle_button(ui, &DOCK_RIGHT_SVG, self.right_panel);
@@ -1 +1,2 @@
 pub mod autosave;
+pub mod render;
@@ -0,0 +1,127 @@
+use icy_engine::{Buffer, Rectangle, TextPane};
+use image::{imageops, RgbaImage};
+
+use crate::TerminalResult;
+
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    pub rect: Option<Rectangle>,
+    pub use_letter_spacing: bool,
+    pub use_aspect_ratio: bool,
+    pub scale: u32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            rect: None,
+            use_letter_spacing: false,
+            use_aspect_ratio: false,
+            scale: 1,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn from_buffer(buf: &Buffer) -> Self {
+        Self {
+            rect: None,
+            use_letter_spacing: buf.use_letter_spacing(),
+            use_aspect_ratio: buf.use_aspect_ratio(),
+            scale: 1,
+        }
+    }
+}
+
+/// Renders the buffer on the CPU - doesn't need a gl context.
+pub fn render_to_image(buf: &Buffer, options: &RenderOptions) -> TerminalResult<RgbaImage> {
+    let rect = options.rect.unwrap_or_else(|| Rectangle::from(0, 0, buf.get_width(), buf.get_height()));
+    let (size, pixels) = buf.render_to_rgba(rect);
+    let Some(mut img) = RgbaImage::from_raw(size.width as u32, size.height as u32, pixels) else {
+        return Err(anyhow::anyhow!("Failed to create image"));
+    };
+
+    if options.use_letter_spacing {
+        img = add_letter_spacing(buf, rect, &img);
+    }
+
+    if options.use_aspect_ratio {
+        let factor = if options.use_letter_spacing { 1.2 } else { 1.35 };
+        let height = (img.height() as f32 * factor).round() as u32;
+        img = imageops::resize(&img, img.width(), height, imageops::FilterType::Triangle);
+    }
+
+    if options.scale > 1 {
+        img = imageops::resize(&img, img.width() * options.scale, img.height() * options.scale, imageops::FilterType::Nearest);
+    }
+    Ok(img)
+}
+
+/// Inserts the 9th pixel column of VGA text mode. Line drawing chars (0xC0-0xDF) extend their last column,
+/// all others get the cell background.
+fn add_letter_spacing(buf: &Buffer, rect: Rectangle, img: &RgbaImage) -> RgbaImage {
+    let font_dims = buf.get_font_dimensions();
+    let font_width = font_dims.width as u32;
+    let font_height = font_dims.height as u32;
+    let cols = (img.width() / font_width.max(1)).min(rect.get_width().max(0) as u32);
+    let rows = (img.height() / font_height.max(1)).min(rect.get_height().max(0) as u32);
+
+    let mut result = RgbaImage::new(cols * (font_width + 1), rows * font_height);
+    for row in 0..rows {
+        for col in 0..cols {
+            let ch = buf.get_char((rect.left() + col as i32, rect.top() + row as i32));
+            let extend_char = (0xC0..=0xDF).contains(&(ch.ch as u32));
+            let (r, g, b) = buf.palette.get_color(ch.attribute.get_background()).get_rgb();
+            let background = image::Rgba([r, g, b, 255]);
+
+            for y in 0..font_height {
+                let src_y = row * font_height + y;
+                for x in 0..font_width {
+                    let pixel = *img.get_pixel(col * font_width + x, src_y);
+                    result.put_pixel(col * (font_width + 1) + x, src_y, pixel);
+                }
+                let last_pixel = if extend_char && font_width > 0 {
+                    *img.get_pixel(col * font_width + font_width - 1, src_y)
+                } else {
+                    background
+                };
+                result.put_pixel(col * (font_width + 1) + font_width, src_y, last_pixel);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use icy_engine::{AttributedChar, TextAttribute};
+
+    use super::*;
+
+    #[test]
+    fn test_letter_spacing() {
+        let mut buf = Buffer::new((2, 1));
+        let mut attr = TextAttribute::default();
+        attr.set_foreground(15);
+        attr.set_background(4);
+        buf.layers[0].set_char((0, 0), AttributedChar::new('\u{DB}', attr));
+        buf.layers[0].set_char((1, 0), AttributedChar::new(' ', attr));
+
+        let font_width = buf.get_font_dimensions().width as u32;
+        let img = render_to_image(&buf, &RenderOptions::default()).unwrap();
+        assert_eq!(2 * font_width, img.width());
+
+        let options = RenderOptions {
+            use_letter_spacing: true,
+            ..Default::default()
+        };
+        let img = render_to_image(&buf, &options).unwrap();
+        assert_eq!(2 * (font_width + 1), img.width());
+
+        // the block extends into the 9th column, the space gets its background there
+        let (r, g, b) = buf.palette.get_color(15).get_rgb();
+        assert_eq!(image::Rgba([r, g, b, 255]), *img.get_pixel(font_width, 0));
+        let (r, g, b) = buf.palette.get_color(4).get_rgb();
+        assert_eq!(image::Rgba([r, g, b, 255]), *img.get_pixel(2 * font_width + 1, 0));
+    }
+}